@@ -0,0 +1,139 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::f32::consts::PI;
+use std::hint::black_box;
+use ulipsync_profile_gen::algorithm::{self, FftPlan};
+use ulipsync_profile_gen::mfcc::{self, MfccBufferPool, MfccConfig};
+use ulipsync_profile_gen::{MelNorm, ResampleQuality, WindowFunction};
+
+const INPUT_RATE: u32 = 44100;
+const TARGET_RATE: u32 = 16000;
+const SAMPLE_COUNT: usize = 1024;
+const MEL_CHANNELS: usize = 24;
+const MFCC_SIZE: usize = 12;
+
+fn window() -> Vec<f32> {
+  (0..SAMPLE_COUNT)
+    .map(|i| {
+      let t = i as f32 / INPUT_RATE as f32;
+      (2.0 * PI * 220.0 * t).sin() + 0.3 * (2.0 * PI * 1800.0 * t).sin()
+    })
+    .collect()
+}
+
+fn bench_dsp(c: &mut Criterion) {
+  let input = window();
+  let mut downsampled = Vec::new();
+  algorithm::resample(
+    &input,
+    INPUT_RATE,
+    TARGET_RATE,
+    ResampleQuality::Low,
+    &mut downsampled,
+  );
+  let mut complex = Vec::new();
+  let mut spectrum = Vec::new();
+  let mut plan = FftPlan::default();
+  algorithm::fft(
+    &mut downsampled.clone(),
+    &mut plan,
+    &mut complex,
+    &mut spectrum,
+  );
+  let mut mel = vec![0.0; MEL_CHANNELS];
+  algorithm::mel_filter_bank(
+    &spectrum,
+    TARGET_RATE as f32,
+    MEL_CHANNELS,
+    (0.0, TARGET_RATE as f32 / 2.0),
+    false,
+    MelNorm::SlaneyArea,
+    &mut mel,
+  );
+  algorithm::power_to_db(&mut mel);
+
+  let mut buf = input.clone();
+  let (mut tmp, mut kernel) = (Vec::new(), Vec::new());
+  c.bench_function("low_pass_filter", |b| {
+    b.iter(|| {
+      buf.copy_from_slice(&input);
+      algorithm::low_pass_filter(
+        black_box(&mut buf),
+        INPUT_RATE as f32,
+        8000.0,
+        500.0,
+        &mut tmp,
+        &mut kernel,
+      );
+    })
+  });
+
+  let mut out = Vec::new();
+  let mut fft_buf = downsampled.clone();
+  c.bench_function("fft", |b| {
+    b.iter(|| {
+      fft_buf.copy_from_slice(&downsampled);
+      algorithm::fft(black_box(&mut fft_buf), &mut plan, &mut complex, &mut out)
+    })
+  });
+
+  // extract_mfcc 只在长度变化时计算窗系数，之后每帧只做 apply_window
+  let mut coeffs = Vec::new();
+  c.bench_function("window", |b| {
+    b.iter(|| {
+      algorithm::window(
+        WindowFunction::Hamming,
+        black_box(downsampled.len()),
+        &mut coeffs,
+      )
+    })
+  });
+
+  let mut windowed = downsampled.clone();
+  c.bench_function("apply_window", |b| {
+    b.iter(|| {
+      windowed.copy_from_slice(&downsampled);
+      algorithm::apply_window(black_box(&mut windowed), &coeffs)
+    })
+  });
+
+  let mut mel_out = vec![0.0; MEL_CHANNELS];
+  c.bench_function("mel_filter_bank", |b| {
+    b.iter(|| {
+      algorithm::mel_filter_bank(
+        black_box(&spectrum),
+        TARGET_RATE as f32,
+        MEL_CHANNELS,
+        (0.0, TARGET_RATE as f32 / 2.0),
+        false,
+        MelNorm::SlaneyArea,
+        &mut mel_out,
+      )
+    })
+  });
+
+  let mut cepstrum = vec![0.0; MFCC_SIZE + 1];
+  c.bench_function("dct", |b| {
+    b.iter(|| algorithm::dct(black_box(&mel), &mut cepstrum, MFCC_SIZE + 1, false))
+  });
+
+  let config = MfccConfig::new(TARGET_RATE, MEL_CHANNELS);
+  let mut pool = MfccBufferPool::new();
+  let mut frame = input.clone();
+  let mut mfcc_out = Vec::new();
+  c.bench_function("extract_mfcc", |b| {
+    b.iter(|| {
+      frame.copy_from_slice(&input);
+      mfcc::extract_mfcc(
+        black_box(&mut frame),
+        0,
+        INPUT_RATE,
+        &config,
+        &mut pool,
+        &mut mfcc_out,
+      );
+    })
+  });
+}
+
+criterion_group!(benches, bench_dsp);
+criterion_main!(benches);
@@ -1,22 +1,582 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
 export declare class ProfileGenerator {
+  /** 所有选项都在构造时校验，不合法时抛出 `InvalidArg`（`fromJson` 同样经过这里）。 */
   constructor(opts: ProfileGeneratorOptions)
-  addSample(audio: Float32Array, phonemeName: string, inputSampleRate: number): void
+  /**
+   * 从之前 `finish` 输出的 profile JSON 恢复，继续追加样本（仍按其中的 `mfccDataCount` 裁剪）。
+   * 恢复 `targetSampleRate`、`melFilterBankChannels`、`mfccNum`、`compareMethod`、
+   * `mfccDataCount`、`sampleCount`、`useStandardization`、`coefficientWeights`、`computeDeltas`、
+   * `keepC0`，其余选项取默认值。`computeDeltas` 的 profile 只读回每帧前半段的系数，差分在 `finish` 时重新计算。
+   * `formatVersion` 比当前版本新的 profile 会被拒绝，没有这个字段的旧 profile 照常读取。
+   */
+  static fromJson(json: string): ProfileGenerator
+  /**
+   * `channels` 为交错声道数，默认 1；多声道时按 `stereoMode` 处理。
+   * `phonemeName` 会去掉首尾空白，为空时报错；名字区分大小写，`A` 和 `a` 是两个音素。
+   * `onProgress` 每处理 `progressInterval` 个窗口（含被跳过的）同步调用一次，参数为已处理的窗口数；
+   * 回调抛错时立即停止，已处理的帧会保留。
+   */
+  addSample(audio: Float32Array, phonemeName: string, inputSampleRate: number, channels?: number | undefined | null, onProgress?: ((arg: number) => void) | undefined | null): void
+  /** 与 `addSample` 相同，额外返回本次处理、跳过的帧数，便于提示输入削波或静音。 */
+  addSampleDetailed(audio: Float32Array, phonemeName: string, inputSampleRate: number, channels?: number | undefined | null): SampleReport
+  /**
+   * 把同一音素的多段录音（例如多次录制的短片段）一次送入，逐段按 `addSample` 处理，
+   * 返回各段计数之和。空的录音直接跳过（计入 `buffersSkippedEmpty`），不会让整批报错。
+   * 整批处理完才按 `retentionPolicy` 裁剪一次。
+   */
+  addSamples(audios: Array<Float32Array>, phonemeName: string, inputSampleRate: number, channels?: number | undefined | null): SampleReport
+  /**
+   * 与 `addSampleDetailed` 相同，但特征提取在 libuv 线程池里进行，不阻塞事件循环，
+   * 适合一次导入很长的录音。使用调用时的配置，帧在 Promise 完成时才写入；
+   * 在此之前调用 `finish` 不会包含这些帧。不支持 `streaming` 模式。
+   * `onProgress` 与 `addSample` 相同，从后台线程异步投递到事件循环，不会阻塞提取。
+   */
+  addSampleAsync(audio: Float32Array, phonemeName: string, inputSampleRate: number, channels?: number | undefined | null, onProgress?: (((arg: number) => void)) | undefined | null): Promise<SampleReport>
+  /**
+   * 输入为 16 位整数 PCM（单声道），每个样本除以 32768 转成浮点后与 `addSample` 相同。
+   * `-32768` 正好对应 -1.0，`32767` 对应略小于 1.0 的 `32767 / 32768`。
+   */
+  addSamplePcm16(audio: Int16Array, phonemeName: string, inputSampleRate: number): void
+  /**
+   * 与 `addSample` 相同，但每提取出一帧就把它作为一行 JSON（`{"phoneme":...,"array":[...]}`）
+   * 传给 `callback`；帧仍会照常累积。回调抛错时立即停止，已处理的帧会保留。
+   */
+  addSampleEmit(audio: Float32Array, phonemeName: string, inputSampleRate: number, callback: (arg: string) => void): void
+  /**
+   * streaming 模式下处理各音素剩下的样本。`pad` 为 true 时把剩余部分补零到一帧再提取，
+   * 否则直接丢弃（不足一帧的样本无法单独成帧）。
+   */
+  flushPending(pad?: boolean | undefined | null): void
+  /**
+   * 每个 `Float32Array` 作为一帧直接送入特征提取（不再切分），所有帧长度必须一致。
+   * 返回得到有限特征值的帧数。
+   */
+  addFrames(frames: Array<Float32Array>, phonemeName: string, inputSampleRate: number): number
+  /**
+   * 把另一个生成器的帧按音素追加进来（按 `mfccDataCount` 丢弃最旧的帧），只在一边出现的
+   * 音素原样带过来。两者的 `targetSampleRate`、`melFilterBankChannels`、系数个数必须一致。
+   * 各音素的输入采样率一并记录（`strictSampleRate` 时不一致直接报错）；不能与自身合并。
+   */
+  merge(other: ProfileGenerator): void
+  /**
+   * 把 `sources` 中各音素的帧按顺序追加到 `target`（按 `mfccDataCount` 裁剪），
+   * 用于从几个安静的音素构造“闭口”之类的派生类别。`removeSources` 为 true 时删除源音素。
+   * 任一源音素不存在时报错，且不做任何修改。
+   */
+  combinePhonemes(sources: Array<string>, target: string, removeSources?: boolean | undefined | null): void
+  /** 直接写入一帧已经算好的特征，返回是否被存下（开启 `rejectExactDuplicates` 时重复帧返回 false）。 */
+  addMfccFrame(frame: Float32Array, phonemeName: string): boolean
   finish(): string
+  /** 与 `finish` 相同，但以 UTF-8 字节的 `Buffer` 返回 JSON，可以直接写文件或流，省去 JS 字符串的转换。 */
+  finishBytes(): Buffer
+  /** 与 `finish` 相同，但直接把 JSON 写入 `path`，不经过 JS 字符串。打开文件失败时不清空数据。 */
+  finishToFile(path: string): void
+  /** 修改比较方式，之后的 `finish`、`compare` 等都按新的方式计算，已累积的帧不受影响。 */
+  setCompareMethod(method: CompareMethod): void
+  /** 丢弃所有已累积的帧，保留配置，可以直接开始新一轮录制。 */
+  reset(): void
+  /**
+   * 只删除一个音素的帧，用于重录单个音。返回该音素之前是否存在。
+   * 与 `addSample` 一样，按名字查找音素时都会先去掉首尾空白。
+   */
+  removePhoneme(name: string): boolean
+  /**
+   * 构造时发现的、不影响使用但可能不是本意的配置问题（例如 `sampleCount` 不是 2 的幂），
+   * 没有时为空数组。
+   */
+  getWarnings(): Array<string>
+  /**
+   * 当前生效的配置，未设置的选项填入默认值（`compareMethod` 反映 `setCompareMethod` 之后的值）。
+   * 没有默认值的可选项（`floatPrecision`、`spectrumBins`、`silenceThresholdDb` 等）未设置时为空。
+   * AGC 关闭时 `agcTargetRms` 等给出的是默认参数。
+   */
+  getOptions(): ProfileGeneratorOptions
+  /** 已有帧的音素名，按字典序排列。 */
+  getPhonemeNames(): Array<string>
+  /**
+   * 该音素在 `finish` 时会输出的帧数（不超过 `mfccDataCount`，`Unlimited` 时为全部帧数），
+   * 未知音素返回 0。
+   */
+  getSampleCount(phonemeName: string): number
+  /** 该音素当前累积的原始帧（未经 `finish` 时的裁剪、归一化等处理），未知音素返回空数组。 */
+  getMfccs(phonemeName: string): Array<Float32Array>
+  /**
+   * 按 `addSample` 的方式分帧（单声道），返回每帧 DCT 之前的 mel 滤波器组输出，
+   * 长度为 `melFilterBankChannels`，按 `logBase` 取对数（默认 dB）。不入库，
+   * 用于画出生成器"听到"的 mel 频谱图，检查录音电平是否合适。需要 `featureType` 为 `Mfcc`。
+   */
+  melSpectrogram(audio: Float32Array, inputSampleRate: number): Array<Float32Array>
+  /** 每个音素的帧数以及逐系数的均值、方差，基于 `finish` 将会输出的帧，不修改已累积的数据。 */
+  stats(): Record<string, PhonemeStats>
+  /**
+   * 用当前累积的数据给一段录音打分：按 `addSample` 的方式分帧提取，取所有帧的平均向量，
+   * 与每个音素保留帧（按 `retentionPolicy` 裁剪到 `mfccDataCount`，不做 CMN、标准化等处理）的平均向量按
+   * `compareMethod`（按 `coefficientWeights` 加权）比较。结果按从最像到最不像排序，
+   * 分数相同时按音素名排序。
+   */
+  compare(audio: Float32Array, inputSampleRate: number): Array<PhonemeScore>
+  /**
+   * 与 `compare` 相同，只返回最像的音素名；分数相同时取字典序最小的名字。
+   * 还没有任何音素时报错。
+   */
+  classify(audio: Float32Array, inputSampleRate: number): string
+  /**
+   * 每个音素 `帧数 × mfccNum` 矩阵的有效秩（奇异值归一化熵的指数）。
+   * 接近 1 说明帧之间高度冗余，接近 `min(帧数, mfccNum)` 说明覆盖的变化更丰富。
+   */
+  effectiveRank(): Record<string, number>
+  /**
+   * 只保留 `keep` 中列出的音素，重新序列化一个已生成的 profile；`keep` 里有不存在的音素时报错。
+   * 输出按当前的结构序列化，所以 `formatVersion` 必须与当前版本相同：更新的 profile 会丢字段，
+   * 更旧的会被误标成当前版本，两种情况都报错。旧 profile 可以先用 `fromJson` + `finish` 升级。
+   */
+  static filterProfile(json: string, keep: Array<string>): string
+  /**
+   * 与参考 profile 逐音素、逐帧、逐系数比较，绝对误差超过 `tolerance` 时返回第一处差异。
+   * 比较的是 `finish` 将会输出的帧（裁剪、归一化等处理之后）。
+   */
+  assertMatches(referenceJson: string, tolerance: number): void
+  /**
+   * 与 `finish` 相同，但输出 MessagePack（需要 `msgpack` feature）。结构和字段名与 JSON 完全一致
+   * （map 形式，键为 `mfccNum`、`melFilterBankChannels` 等），帧多时体积明显更小。
+   */
+  finishMsgpack(): Buffer
+  /**
+   * 把某个音素的帧导出为灰度 PNG（需要 `png` feature）。
+   * 横轴为帧，纵轴为 mel 通道（低频在下）；由 MFCC 逆 DCT 近似还原 log-mel，
+   * `Spectrum` 特征则直接画幅度谱的 dB。整图 min/max 归一化到 0..255，越亮能量越高。
+   */
+  exportSpectrogramPng(phonemeName: string, path: string): void
+  /**
+   * 读取 WAV 文件并按 `addSample` 处理（需要 `wav` feature）。采样率和声道数取自文件头，
+   * 多声道按 `stereoMode` 处理（默认 downmix）。支持 16 位整数 PCM 和 32 位浮点，其他格式报错。
+   */
+  addSampleFromWav(path: string, phonemeName: string): void
+}
+
+export interface BuildInfo {
+  msgpack: boolean
+  png: boolean
+  simd: boolean
+  rayon: boolean
+  wav: boolean
+  pipelineVersion: number
 }
 
 export declare const enum CompareMethod {
   L1Norm = 0,
   L2Norm = 1,
-  CosineSimilarity = 2
+  CosineSimilarity = 2,
+  ChebyshevNorm = 3,
+  /**
+   * 两个向量各自减去均值后的余弦相似度（皮尔逊相关系数），不受整体电平偏移影响。
+   * uLipSync 本身不支持，写进 profile 后只能由自己的匹配代码读取。
+   */
+  PearsonCorrelation = 4
+}
+
+export declare const enum FeatureType {
+  Mfcc = 0,
+  Spectrum = 1
+}
+
+export declare const enum LogBase {
+  Db10 = 0,
+  Ln = 1,
+  Log2 = 2
+}
+
+export declare const enum MelNorm {
+  None = 0,
+  SlaneyArea = 1
+}
+
+export declare const enum NormalizeMode {
+  Peak = 0,
+  Rms = 1
+}
+
+export interface PhonemeScore {
+  name: string
+  /** `compareMethod` 为 `CosineSimilarity` / `PearsonCorrelation` 时是相似度，否则是距离 */
+  score: number
+  /** 为 true 时分数越高越相近（余弦相似度、相关系数），否则越低越相近 */
+  higherIsBetter: boolean
+}
+
+export interface PhonemeStats {
+  count: number
+  mean: Array<number>
+  variance: Array<number>
 }
 
 export interface ProfileGeneratorOptions {
   targetSampleRate: number
   melFilterBankChannels: number
+  /** 每帧保留的 MFCC 系数个数，默认 12。第 0 项被跳过，所以最多为 `melFilterBankChannels - 1`。 */
+  mfccNum?: number
   compareMethod?: CompareMethod
+  /**
+   * 距离计算前每个系数乘上的权重，长度必须等于每帧的系数个数（`mfccNum`，`keepC0` 时加 1；
+   * `Spectrum` 特征要设置 `spectrumBins` 并与之相等），权重不能为负。低阶系数给更大的权重
+   * 可以让 `compare` 更看重包络的大致形状。同样用于 `includeTrajectory`、`includeConfidence`
+   * 和 `dedupThreshold`，开启 `computeDeltas` 后差分部分沿用对应系数的权重。会写进 profile，
+   * `fromJson` 读回后保留。默认不加权。
+   */
+  coefficientWeights?: Array<number>
+  /** 余弦相似度（和相关系数）分母上加的小量，默认 1e-12。全零帧（例如 CMN 之后）与任何帧的相似度为 0。 */
+  cosineEpsilon?: number
+  /**
+   * 每个音素最多保留的帧数，默认 16。超过 100000 会报错，防止误设过大的值导致内存
+   * 随录音长度无限增长；确实需要时设置 `allowLargeCapacity`。
+   */
   mfccDataCount?: number
   sampleCount?: number
+  /**
+   * 相邻两帧起点的间隔（样本数），默认等于 `sampleCount`（不重叠）。取 `sampleCount / 2`
+   * 即 50% 重叠，跨帧边界的短音素更容易得到完整的帧。末尾不足一帧的部分仍然丢弃。
+   */
+  hopSize?: number
+  /**
+   * 为 true 时 `finish` 对每个音素逐系数做标准化（减去该音素所有帧的均值再除以标准差），
+   * 标准差接近 0 的系数只减均值。输出里的 `useStandardization` 同时置 1。
+   * 注意 `fromJson` 读入的帧已经标准化过，继续追加后会再做一次。
+   */
   useStandardization?: boolean
+  /**
+   * 超过 `mfccDataCount` 时保留哪些帧。默认 `Fifo`：丢掉最旧的帧，只保留最近的部分。
+   * `Representative`：用 k-means（k = `mfccDataCount`）把该音素的帧聚类，每类保留离中心最近的一帧
+   * （仍按时间顺序排列），更均匀地覆盖整段录音里的变化；帧数达到两倍上限时裁剪一次，
+   * `finish` 时再裁剪到上限。`Unlimited`：保留所有帧，忽略 `mfccDataCount`，输出里的
+   * `mfccDataCount` 为帧数最多的音素的帧数；内存随录音长度线性增长。
+   */
+  retentionPolicy?: RetentionPolicy
+  /**
+   * 为 true 时 `addSample` 保留所有帧，到 `finish` 时才统一裁剪到 `mfccDataCount`。
+   * 裁剪结果与调用顺序无关，但在 `finish` 之前内存会随录音长度线性增长。
+   */
+  deferTrim?: boolean
+  /**
+   * 为 true 时每帧都带上前面 `blen - 1` 个真实样本一起做低通（`blen` 为 FIR 核长，约
+   * `3.1 * inputSampleRate / 500`），这样保留下来的帧不含滤波器瞬态；前面凑不够这段预热的
+   * 帧（起点小于 `blen - 1` 的帧）会被丢弃。
+   */
+  discardFilterTransient?: boolean
+  /**
+   * 多声道输入（`channels > 1`）的处理方式，默认 `Downmix`：立体声取平均，5.1（L R C LFE Ls Rs）
+   * 按 ITU-R BS.775 加权（中置、环绕 -3 dB，忽略 LFE），结果按权重之和归一化，不会削波。
+   * `Louder` 对每帧分别比较各声道能量，只用能量最大的那个声道提取特征。
+   */
+  stereoMode?: StereoMode
+  /**
+   * `finish` 时把每个系数舍入到指定的有效数字位数（1..=9），缩短 JSON 文本。
+   * MFCC 的数值范围大约是几十，取 4 位时误差在 0.01 量级，对匹配结果基本没有影响；
+   * 不设置时保持 f32 的完整精度。
+   */
+  floatPrecision?: number
+  /** 为 true 时 `finish` 对每个音素做倒谱均值归一化（减去该音素所有帧的均值向量）。 */
+  cmn?: boolean
+  /**
+   * 为 true 时 `finish` 做全局倒谱均值方差归一化（CMVN）：用所有音素全部保留帧的逐系数
+   * 均值和标准差，对每一帧减均值、除以标准差（标准差接近 0 的系数只减均值），用来去掉
+   * 麦克风等信道的影响。在逐音素的 `cmn` / `useStandardization` 之后进行。
+   * **输出的系数不再是原始 MFCC**，匹配时输入也需要按同样的统计量归一化。
+   */
+  cmvn?: boolean
+  /**
+   * CMN 均值的统计范围。默认 false：只用裁剪到 `mfccDataCount` 之后保留下来的帧；
+   * true：用裁剪之前该音素出现过的所有帧（包括已被丢弃的旧帧）。
+   */
+  cmnBeforeTrim?: boolean
+  /**
+   * 存储的特征类型，默认 `Mfcc`。`Spectrum` 直接保存单边幅度谱（跳过 mel/dB/DCT），
+   * 此时输出的 `mfccNum` 为谱长度。
+   */
+  featureType?: FeatureType
+  /**
+   * `Spectrum` 模式下把单边谱按频率均分取平均，压缩到固定的 bin 数。不设置时保留全部
+   * `n/2 + 1` 个 bin，长度取决于重采样后的窗长，同一个 profile 应使用相同的输入采样率。
+   */
+  spectrumBins?: number
+  /**
+   * 为 true 时 `finish` 给每个音素输出 `confidence`（0..1）：取各帧到该音素中心的平均距离
+   * （按 `compareMethod`）的倒数，再除以所有音素中的最大值。帧越集中越接近 1，
+   * 少于 2 帧的音素为 0。
+   */
+  includeConfidence?: boolean
+  /**
+   * 为 true 时 `finish` 给每个音素输出 `trajectory`：按保存顺序相邻两帧之间的距离
+   * （按 `compareMethod`），长度为帧数 - 1。曲线尖峰多说明录音不稳定。
+   */
+  includeTrajectory?: boolean
+  /**
+   * 为 true 时在分帧之前对整段输入做自动增益（AGC），缓慢地把响度拉向 `agcTargetRms`，
+   * 适合同一段录音里音量忽大忽小的情况，默认关闭。
+   * 之后每帧的 `Normalize` 仍会把该帧缩放到峰值 1，所以 AGC 不改变帧与帧之间的整体电平，
+   * 作用在于平滑帧内的音量起伏、让 `Louder` 的声道选择不受整体音量影响；
+   * 如果 `pipelineOrder` 把 `Normalize` 放在最前面，AGC 的效果也会被它覆盖一部分。
+   */
+  agc?: boolean
+  /** AGC 的目标 RMS，默认 0.1。 */
+  agcTargetRms?: number
+  /** AGC 包络上升时间（毫秒），默认 5。 */
+  agcAttackMs?: number
+  /** AGC 包络下降时间（毫秒），默认 200。 */
+  agcReleaseMs?: number
+  /**
+   * mel 能量取对数的方式，默认 `Db10`（`10 * log10`，与之前的输出一致）。
+   * `Ln` / `Log2` 与 `Db10` 只差常数倍，DCT 是线性的，所以 MFCC 也只是整体缩放。
+   */
+  logBase?: LogBase
+  /**
+   * 为 true 时，与该音素已有帧逐位相同的新帧会被跳过（每个音素维护一份帧哈希），
+   * 重复导入同一批数据不会产生重复帧。
+   */
+  rejectExactDuplicates?: boolean
+  /**
+   * 设置后，新帧与该音素最近保存的一帧的距离（按 `compareMethod`；`CosineSimilarity` /
+   * `PearsonCorrelation` 时用 `1 - 相似度`）小于这个值就跳过，长时间保持同一个音时不会让几乎相同的帧占满 `mfccDataCount`。
+   * 与 `rejectExactDuplicates` 不同，只和上一帧比较。
+   */
+  dedupThreshold?: number
+  /**
+   * 降采样之后、FFT 之前三个步骤的执行顺序，必须恰好包含 `PreEmphasis`、`Window`、
+   * `Normalize` 各一次。默认 `[PreEmphasis, Window, Normalize]`，用于复刻其他工具的特征定义。
+   */
+  pipelineOrder?: Array<Stage>
+  /**
+   * 为 true 时 mel 滤波器组改用 Slaney 刻度（1000 Hz 以下线性、以上对数，同 librosa 的默认值），
+   * 默认 false 为 HTK 公式（与之前的输出一致）。
+   */
+  useSlaneyMel?: boolean
+  /** mel 滤波器组覆盖的最低频率（Hz），默认 0。语音一般取 80 左右，避免把滤波器浪费在低频噪声上。 */
+  melFmin?: number
+  /** mel 滤波器组覆盖的最高频率（Hz），默认 `targetSampleRate / 2`，不能超过它，且要大于 `melFmin`。 */
+  melFmax?: number
+  /**
+   * mel 三角滤波器的归一化方式。默认 `SlaneyArea`：每个滤波器乘以 `2 / (fEnd - fBegin)`，
+   * 面积与带宽无关（也就是之前一直使用的方式，与 librosa 的 `norm="slaney"` 相同）；
+   * `None` 不归一化，所有三角形峰值为 1。
+   */
+  melNorm?: MelNorm
+  /**
+   * 为 true 时 mel 滤波器组改用与 uLipSync C# 版 `MelFilterBank` 逐行对应的实现：起止点
+   * `ceil` / `floor`、中心点银行家舍入、累加区间和顺序都与 C# 相同，全程 f32。只支持 uLipSync
+   * 自己的设置，不能与 `useSlaneyMel`、`melFmin`、`melFmax`、`melNorm: None`、`highPrecision` 同时使用。
+   * 默认 false；默认设置下两种实现的输出相同，只有三角形退化时（通道数相对 FFT 长度过多）
+   * 这里与 C# 一样照除，不把该通道置 0。
+   */
+  ulipsyncExact?: boolean
+  /**
+   * `Normalize` 阶段的方式，默认 `Peak`（缩放到峰值 1，与之前一致）。`Rms` 把整帧的 RMS
+   * 缩放到满幅正弦的 RMS（`1 / sqrt(2)`），单个削波样本或爆音不会压低整帧的电平。
+   */
+  normalizeMode?: NormalizeMode
+  /**
+   * `Normalize` 阶段的目标峰值，默认 1（`Rms` 模式下目标为该值乘以 `1 / sqrt(2)`）。
+   * 设为 0 跳过归一化，保留帧与帧之间的相对响度，这时 `keepC0` 输出的 c0 才能当作能量使用。
+   */
+  normalizePeak?: number
+  /** 为 true 时在 FFT 之前减去每帧的均值，去掉部分麦克风带来的直流偏置，默认 false。 */
+  removeDc?: boolean
+  /**
+   * 设置后在重采样之后、预加重之前做一阶高通（截止频率 Hz，必须小于 `targetSampleRate / 2`），
+   * 压低空调、手持麦克风等低频噪声对前几个 mel 通道的影响，语音一般取 80 左右。默认不做。
+   */
+  highpassCutoff?: number
+  /**
+   * 重采样之后给每帧叠加的三角分布（TPDF）抖动的最大幅度（满幅为 1），默认 0 不加。
+   * 由 8 位等低位深音源转换来的录音有台阶状的量化误差，会在频谱上形成虚假的谐波线；
+   * 加一点抖动可以让这些谐波线淹没在平坦的噪声里，代价是噪声底略微抬高。8 位音源取 `1 / 128`（1 LSB）左右。
+   * 随机数的种子由每帧的样本值决定，同样的输入每次运行的结果相同。
+   */
+  ditherAmount?: number
+  /** 分析窗，默认 `Hamming`。 */
+  window?: WindowFunction
+  /** 预加重系数 `y[n] = x[n] - a * x[n - 1]`，取值 [0, 1)，默认 0.97。为 0 时信号保持不变。 */
+  preEmphasisCoeff?: number
+  /**
+   * 正弦倒谱提升（liftering）参数 L：第 n 个倒谱系数乘以 `1 + (L / 2) * sin(pi * n / L)`，
+   * 用来平衡高阶系数的幅度，常用值 22。不设置或为 0 时不做。
+   */
+  lifter?: number
+  /**
+   * 为 true 时 DCT 使用正交归一化（第 0 项乘 `sqrt(1/N)`，其余乘 `sqrt(2/N)`，N 为 mel 通道数），
+   * 与 librosa 等的 `norm="ortho"` 一致。默认 false，系数与之前相同。
+   */
+  dctOrtho?: boolean
+  /**
+   * 重采样前抗混叠低通的过渡带宽（Hz），默认 500。截止频率为目标采样率和输入采样率中
+   * 较低者的一半，必须大于这个值：构造时按 `targetSampleRate / 2` 检查，输入采样率在
+   * `addSample` 等调用时检查，不满足时报错。过渡带越窄高频保留得越多，但 FIR 核越长、越慢。
+   */
+  lpfTransitionRange?: number
+  /**
+   * 非整数倍重采样的方式。默认 `Low` 为线性插值（与之前一致）；`High` 改用 Kaiser 窗 sinc 插值，
+   * 降采样时截止频率跟着降到目标奈奎斯特频率，减少高频混叠到上面几个 mel 通道，计算量大一些。
+   * 采样率相同或整数倍降采样时不受影响。
+   */
+  resampleQuality?: ResampleQuality
+  /**
+   * 为 true 时 FFT、mel 滤波、取对数和 DCT 改用 f64 计算，只在输出时转回 f32，
+   * 与双精度的参考实现（例如 numpy / librosa）对比时误差更小。结果与默认的 f32 略有差别，
+   * 速度稍慢。默认 false。
+   */
+  highPrecision?: boolean
+  /**
+   * 为 true 时 FFT 之前把重采样后的窗口补零到下一个 2 的幂。重采样会改变窗口长度，
+   * 即使 `sampleCount` 是 2 的幂，送进 FFT 的长度也不一定是，这时 FFT 会慢很多。
+   * 补零不增加信息，只让频谱更密，mel 滤波器随之调整；系数与不补零时略有差别。
+   * `Spectrum` 特征未设置 `spectrumBins` 时每帧长度也随之变化。默认 false。
+   */
+  padFftToPow2?: boolean
+  /**
+   * 为 true 时在每帧最前面保留第 0 个倒谱系数（帧的对数能量，有助于区分清音和浊音），
+   * 每帧长度和输出的 `mfccNum` 都变为 `mfccNum + 1`。默认 false，与 uLipSync 的 profile 一致。
+   * 开启时 profile 里记录 `keepC0`，`fromJson` 读回后继续保留 c0。
+   */
+  keepC0?: boolean
+  /** 为 true 时不检查 `mfccDataCount` 的上限。 */
+  allowLargeCapacity?: boolean
+  /**
+   * 为 true 时 `addSample` 会把每个音素末尾不足一帧的样本留下来，拼到下一次同一音素的输入前面，
+   * 适合把录音切成任意长度的小块连续送入。录音结束时调用 `flushPending` 处理剩下的部分；
+   * `finish` / `reset` 会直接丢弃未处理的样本。两次调用的声道数和采样率必须一致。
+   */
+  streaming?: boolean
+  /**
+   * 为 true 时，短到一帧都凑不出来的输入（不足 `sampleCount`，开启 `discardFilterTransient`
+   * 时还要加上预热长度）会补零到一帧再处理。默认 false：这样的输入不产生任何帧，
+   * `addSample` 也不会报错。streaming 模式下短输入会留到下一次，不受此选项影响。
+   */
+  padShortInput?: boolean
+  /**
+   * 为 true 时先把输入里的 NaN / inf 样本替换成 0（计入 `samplesSanitized`），
+   * 避免个别解码错误的样本让整帧特征变成非有限值而被丢弃。默认 false。
+   */
+  sanitizeInput?: boolean
+  /**
+   * 为 true 时，同一音素后续录音的采样率与第一次不同就报错。默认 false，只在返回的
+   * `SampleReport.expectedSampleRate` 里提示。不同采样率重采样后的特征会有细微差别。
+   */
+  strictSampleRate?: boolean
+  /** `addSample` / `addSampleAsync` 的 `onProgress` 回调间隔（窗口数），默认 256。 */
+  progressInterval?: number
+  /**
+   * 为 true 时 `addSample` 把各帧分到多个线程并行提取，结果仍按时间顺序入库，输出与串行一致。
+   * 适合一次送入很长的录音；需要编译时开启 `rayon` feature，否则构造时报错。
+   */
+  parallel?: boolean
+  /**
+   * 为 true 时 `finish` / `finishToFile` 输出带缩进的 JSON，便于阅读和 diff。
+   * 默认 false，输出紧凑格式（与之前一致）。
+   */
+  pretty?: boolean
+  /**
+   * 为 true 时 `finish` 在每帧后面拼上一阶差分（相邻保留帧的中心差分 `(c[t+1] - c[t-1]) / 2`，
+   * 首尾帧用单边差分），输出的 `mfccNum` 翻倍。**生成的 profile 与普通 uLipSync 不兼容**，
+   * 只在下游也按这个布局匹配时开启。差分按帧的保存顺序计算，多次 `addSample` 的帧会首尾相接。
+   * 与 `cmn` 同时开启时只对系数部分减均值，差分保持不变。profile 里会记录 `computeDeltas`，
+   * `fromJson` 据此恢复。
+   */
+  computeDeltas?: boolean
+  /**
+   * 静音门限（dBFS，满幅为 0 dB，必须 <= 0）。分析窗内原始样本（AGC 之后、预加重和归一化之前）
+   * 的 RMS 低于门限时跳过该帧，计入 `framesSkippedSilence`。录音里字与字之间的空白
+   * 归一化后会变成近似随机的特征，一般取 -50 左右。不设置时不做门限。
+   */
+  silenceThresholdDb?: number
+  /**
+   * 首尾静音门限（dBFS，必须 <= 0）。分帧之前先去掉整段录音开头和结尾幅度一直低于门限的部分
+   * （两边各留 10 ms），只裁两端，中间的停顿保留。不设置时不裁剪；不支持 `streaming` 模式。
+   */
+  trimSilenceDb?: number
+}
+
+export declare const enum ResampleQuality {
+  Low = 0,
+  High = 1
 }
+
+export declare const enum RetentionPolicy {
+  Fifo = 0,
+  Representative = 1,
+  Unlimited = 2
+}
+
+export interface SampleReport {
+  /** 成功提取出特征的帧数 */
+  framesProcessed: number
+  /** 特征含 NaN / inf 被跳过的帧数（通常是输入削波或全静音） */
+  framesDroppedNonfinite: number
+  /** 末尾凑不满一帧而没有处理的窗口数（streaming 模式下这部分会留到下一次，记为 0） */
+  framesDroppedShort: number
+  /** 低于 `silenceThresholdDb` 被当作静音跳过的帧数 */
+  framesSkippedSilence: number
+  /** `sanitizeInput` 开启时被替换成 0 的 NaN / inf 输入样本数 */
+  samplesSanitized: number
+  /** `addSamples` 中因为为空而跳过的录音数 */
+  buffersSkippedEmpty: number
+  /**
+   * 该音素之前的录音用的是另一个采样率时，为之前（第一次）的采样率；一致时不设置。
+   * 开启 `strictSampleRate` 时这种情况直接报错
+   */
+  expectedSampleRate?: number
+}
+
+export interface Segment {
+  startSample: number
+  endSample: number
+}
+
+export interface SegmentOptions {
+  /** 计算 RMS 的帧长（毫秒），默认 20。 */
+  frameMs?: number
+  /** 相对最响帧高于这个电平（dB，<= 0）时开始一段，默认 -30。 */
+  onDb?: number
+  /** 相对最响帧低于这个电平（dB，不高于 `onDb`）时结束一段，默认 -40。 */
+  offDb?: number
+  /**
+   * 绝对电平下限（dBFS，<= 0），默认 -60。两个相对门限都不会低于它，
+   * 整段录音都低于它（例如全静音）时不返回任何段。
+   */
+  floorDb?: number
+  /** 短于这个时长（毫秒）的段丢弃，按帧数向上取整，默认 60。 */
+  minDurationMs?: number
+}
+
+export declare const enum Stage {
+  PreEmphasis = 0,
+  Window = 1,
+  Normalize = 2
+}
+
+export declare const enum StereoMode {
+  Downmix = 0,
+  Louder = 1,
+  Left = 2,
+  Right = 3
+}
+
+export declare const enum WindowFunction {
+  Hamming = 0,
+  Hann = 1,
+  Blackman = 2
+}
+
+/** 当前二进制编译进了哪些可选 feature，用来在 JS 侧提前判断方法是否可用。 */
+export declare function buildInfo(): BuildInfo
+
+/**
+ * 对一个窗口直接跑一遍特征提取（与 `addSample` 的单帧处理相同，使用默认选项），
+ * 不需要创建 `ProfileGenerator`。整个 `audio` 作为一帧，不做切分。
+ */
+export declare function extractFeatures(audio: Float32Array, inputSampleRate: number, targetSampleRate: number, melFilterBankChannels: number): Float32Array
+
+/**
+ * 按能量把一段连续录音切成若干有声区间（`[startSample, endSample)`）。
+ * 默认以 20ms 为一帧计算 RMS，相对最响帧高于 -30dB 开始、低于 -40dB 结束，
+ * 绝对电平低于 -60 dBFS 的帧不算有声，短于 60ms 的段丢弃；各项可以通过 `options` 调整。
+ */
+export declare function segmentByEnergy(audio: Float32Array, inputSampleRate: number, options?: SegmentOptions | undefined | null): Array<Segment>
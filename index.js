@@ -395,3 +395,15 @@ if (!nativeBinding) {
 module.exports = nativeBinding
 module.exports.ProfileGenerator = nativeBinding.ProfileGenerator
 module.exports.CompareMethod = nativeBinding.CompareMethod
+module.exports.FeatureType = nativeBinding.FeatureType
+module.exports.LogBase = nativeBinding.LogBase
+module.exports.MelNorm = nativeBinding.MelNorm
+module.exports.NormalizeMode = nativeBinding.NormalizeMode
+module.exports.ResampleQuality = nativeBinding.ResampleQuality
+module.exports.RetentionPolicy = nativeBinding.RetentionPolicy
+module.exports.Stage = nativeBinding.Stage
+module.exports.StereoMode = nativeBinding.StereoMode
+module.exports.WindowFunction = nativeBinding.WindowFunction
+module.exports.buildInfo = nativeBinding.buildInfo
+module.exports.extractFeatures = nativeBinding.extractFeatures
+module.exports.segmentByEnergy = nativeBinding.segmentByEnergy
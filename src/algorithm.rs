@@ -1,6 +1,10 @@
-use rustfft::{num_complex::Complex32, FftPlanner};
-use std::cell::RefCell;
+use crate::{MelNorm, ResampleQuality, WindowFunction};
+use realfft::num_complex::Complex;
+use realfft::num_traits::{Float, FloatConst, ToPrimitive};
+use realfft::{FftNum, RealFftPlanner, RealToComplex};
 use std::f32::consts::PI;
+use std::ops::Range;
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
 
 #[inline]
 fn get_max_value(slice: &[f32]) -> f32 {
@@ -17,6 +21,172 @@ pub fn normalize(data: &mut [f32], peak: f32) {
   }
 }
 
+// 按 RMS 缩放到 target，能量接近 0 时保持不变
+pub fn normalize_rms(data: &mut [f32], target: f32) {
+  let r = rms(data);
+  if r > f32::EPSILON {
+    let k = target / r;
+    for x in data.iter_mut() {
+      *x *= k;
+    }
+  }
+}
+
+pub fn remove_dc(data: &mut [f32]) {
+  if data.is_empty() {
+    return;
+  }
+  let mean = data.iter().sum::<f32>() / data.len() as f32;
+  for x in data.iter_mut() {
+    *x -= mean;
+  }
+}
+
+// SplitMix64，只用来生成可复现的抖动，不要求密码学强度
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+  fn next_f32(&mut self) -> f32 {
+    self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    // 取高 24 位，[0, 1)
+    (z >> 40) as f32 / (1u64 << 24) as f32
+  }
+}
+
+// 叠加幅度在 [-amount, amount] 内的三角分布（TPDF）抖动。种子由这一帧的样本值（FNV-1a）得到，
+// 同样的输入总是得到同样的结果，与处理顺序和线程无关
+pub fn dither(data: &mut [f32], amount: f32) {
+  let seed = data.iter().fold(0xCBF2_9CE4_8422_2325u64, |h, x| {
+    (h ^ x.to_bits() as u64).wrapping_mul(0x0100_0000_01B3)
+  });
+  let mut rng = SplitMix64(seed);
+  for x in data.iter_mut() {
+    *x += (rng.next_f32() - rng.next_f32()) * amount;
+  }
+}
+
+// 一阶 RC 高通：y[n] = a * (y[n-1] + x[n] - x[n-1])，a = RC / (RC + dt)，第一个样本原样保留
+pub fn high_pass_filter(data: &mut [f32], sample_rate: f32, cutoff: f32) {
+  let rc = 1.0 / (2.0 * PI * cutoff);
+  let dt = 1.0 / sample_rate;
+  let a = rc / (rc + dt);
+  let Some(&first) = data.first() else {
+    return;
+  };
+  let (mut prev_x, mut prev_y) = (first, first);
+  for x in data.iter_mut().skip(1) {
+    let y = a * (prev_y + *x - prev_x);
+    prev_x = *x;
+    prev_y = y;
+    *x = y;
+  }
+}
+
+// 交错多声道数据去掉首尾静音后的范围（样本下标）：从第一个到最后一个有声道幅度超过
+// threshold 的采样帧，两边各多留 margin 帧。整段都低于门限时返回空范围
+pub fn trim_silence(data: &[f32], channels: usize, threshold: f32, margin: usize) -> Range<usize> {
+  let frames = data.len() / channels;
+  let loud = |i: &usize| {
+    data[i * channels..(i + 1) * channels]
+      .iter()
+      .any(|x| x.abs() > threshold)
+  };
+  let Some(first) = (0..frames).find(loud) else {
+    return 0..0;
+  };
+  let last = (first..frames).rfind(loud).unwrap_or(first);
+  let start = first.saturating_sub(margin);
+  let end = (last + 1 + margin).min(frames);
+  start * channels..end * channels
+}
+
+#[inline]
+pub fn rms(data: &[f32]) -> f32 {
+  if data.is_empty() {
+    return 0.0;
+  }
+  (data.iter().map(|&x| x * x).sum::<f32>() / data.len() as f32).sqrt()
+}
+
+// AGC 最多放大 20 dB，避免把静音段的底噪拉上来
+const AGC_MAX_GAIN: f32 = 10.0;
+
+#[derive(Clone)]
+pub struct Agc {
+  pub target_rms: f32,
+  pub attack_ms: f32,
+  pub release_ms: f32,
+}
+
+// 自动增益：一阶包络跟踪功率（上升用 attack，下降用 release），按 target_rms / sqrt(包络) 逐样本缩放。
+// 多声道交错数据取各声道功率最大值作为包络输入，所有声道使用同一增益
+pub fn agc(data: &mut [f32], channels: usize, sample_rate: u32, params: &Agc) {
+  let coeff = |ms: f32| (-1000.0 / (ms.max(f32::EPSILON) * sample_rate as f32)).exp();
+  let attack = coeff(params.attack_ms);
+  let release = coeff(params.release_ms);
+
+  // 包络初值取开头一个 release 时长内的平均功率，避免起始处增益突变
+  let head = ((params.release_ms * sample_rate as f32 / 1000.0) as usize).max(1) * channels;
+  let head = &data[..head.min(data.len())];
+  let mut env =
+    head.iter().map(|&x| x * x).sum::<f32>() / head.len().max(1) as f32 * channels as f32;
+
+  for frame in data.chunks_exact_mut(channels) {
+    let power = frame.iter().map(|&x| x * x).fold(0.0, f32::max);
+    let a = if power > env { attack } else { release };
+    env = a * env + (1.0 - a) * power;
+    let gain = (params.target_rms / env.sqrt().max(f32::MIN_POSITIVE)).min(AGC_MAX_GAIN);
+    for x in frame.iter_mut() {
+      *x *= gain;
+    }
+  }
+}
+
+// 按帧 RMS 做带回差的门限分段：高于 on_db 开始，低于 off_db 结束（均相对最响的一帧），
+// 两个门限都不低于绝对电平 floor_db（dBFS），整段低于 floor_db 时没有任何段。
+// 短于 min_frames 帧的段丢弃。返回 [start, end) 样本区间
+pub fn energy_segments(
+  data: &[f32],
+  frame_len: usize,
+  on_db: f32,
+  off_db: f32,
+  floor_db: f32,
+  min_frames: usize,
+) -> Vec<(usize, usize)> {
+  let levels: Vec<f32> = data
+    .chunks(frame_len.max(1))
+    .map(|frame| 20.0 * rms(frame).max(f32::MIN_POSITIVE).log10())
+    .collect();
+  let peak = levels.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+  let on_db = (peak + on_db).max(floor_db);
+  let off_db = (peak + off_db).max(floor_db);
+
+  let mut segments = Vec::new();
+  let mut open: Option<usize> = None;
+  for (i, &level) in levels.iter().enumerate() {
+    match open {
+      None if level >= on_db => open = Some(i),
+      Some(begin) if level < off_db => {
+        if i - begin >= min_frames {
+          segments.push((begin * frame_len, i * frame_len));
+        }
+        open = None;
+      }
+      _ => {}
+    }
+  }
+  if let Some(begin) = open {
+    if levels.len() - begin >= min_frames {
+      segments.push((begin * frame_len, data.len()));
+    }
+  }
+  segments
+}
+
 fn low_pass_filter_kernel(data: &mut [f32], cutoff: f32, tmp: &[f32], b: &mut [f32]) {
   let blen = b.len();
 
@@ -36,11 +206,13 @@ fn low_pass_filter_kernel(data: &mut [f32], cutoff: f32, tmp: &[f32], b: &mut [f
   }
 }
 
-pub fn low_pass_filter(data: &mut [f32], sample_rate: f32, cutoff: f32, range: f32) {
-  let cutoff_n = (cutoff - range) / sample_rate;
-  let range_n = range / sample_rate;
+// 核长取偶数，中心不会落在 sinc 的 0/0 上
+const MIN_LPF_KERNEL_LEN: usize = 4;
 
-  let tmp = data.to_vec();
+// 过渡带相对采样率很宽时算出的核长会是 0，这里至少取最短核长，不会得到空核。
+// 截止频率减去过渡带不为正时核本身没有意义，调用方要先检查（见 lib.rs 的 check_lpf_range）
+pub fn low_pass_filter_len(sample_rate: f32, range: f32) -> usize {
+  let range_n = range / sample_rate;
 
   let mut n = (3.1 / range_n).round_ties_even() as i32;
 
@@ -48,22 +220,115 @@ pub fn low_pass_filter(data: &mut [f32], sample_rate: f32, cutoff: f32, range: f
     n += 1;
   }
 
-  let blen = if n > 0 { n as usize } else { 0 };
-  let mut b = vec![0.0; blen];
+  (n.max(0) as usize).max(MIN_LPF_KERNEL_LEN)
+}
+
+// tmp / b 为调用方提供的临时缓冲，长度不够时会自动扩展
+pub fn low_pass_filter(
+  data: &mut [f32],
+  sample_rate: f32,
+  cutoff: f32,
+  range: f32,
+  tmp: &mut Vec<f32>,
+  b: &mut Vec<f32>,
+) {
+  let cutoff_n = (cutoff - range) / sample_rate;
+
+  tmp.clear();
+  tmp.extend_from_slice(data);
+
+  let blen = low_pass_filter_len(sample_rate, range);
+  b.clear();
+  b.resize(blen, 0.0);
+
+  low_pass_filter_kernel(data, cutoff_n, tmp, b);
+}
+
+// High 模式下 sinc 核单侧的过零点个数
+const SINC_ZERO_CROSSINGS: f32 = 8.0;
+// Kaiser 窗的 beta，旁瓣约 -80 dB
+const KAISER_BETA: f32 = 8.6;
+
+// 第一类零阶修正贝塞尔函数，级数展开
+fn bessel_i0(x: f32) -> f32 {
+  let q = x * x / 4.0;
+  let mut term = 1.0;
+  let mut sum = 1.0;
+  for k in 1..32 {
+    term *= q / (k * k) as f32;
+    sum += term;
+    if term < sum * 1e-9 {
+      break;
+    }
+  }
+  sum
+}
+
+// Kaiser 窗加权的 sinc 插值；降采样时截止频率跟着降到目标奈奎斯特频率，核按比例加宽。
+// 超出 input 的部分按 0 处理，权重按实际参与的和归一化，直流增益保持 1
+fn resample_sinc(input: &[f32], df: f32, out_len: usize, out: &mut Vec<f32>) {
+  let fc = 0.5 * (1.0 / df).min(1.0);
+  let half = SINC_ZERO_CROSSINGS / (2.0 * fc);
+  let norm = bessel_i0(KAISER_BETA);
+  for j in 0..out_len {
+    let center = df * j as f32;
+    let lo = (center - half).ceil().max(0.0) as usize;
+    let hi = ((center + half).floor() as usize).min(input.len().saturating_sub(1));
+    let mut sum = 0.0;
+    let mut weight = 0.0;
+    for (i, &x) in input.iter().enumerate().take(hi + 1).skip(lo) {
+      let d = i as f32 - center;
+      let r = d / half;
+      let kaiser = bessel_i0(KAISER_BETA * (1.0 - r * r).max(0.0).sqrt()) / norm;
+      let arg = 2.0 * fc * d;
+      let sinc = if arg.abs() < 1e-6 {
+        1.0
+      } else {
+        (PI * arg).sin() / (PI * arg)
+      };
+      let w = sinc * kaiser;
+      sum += w * x;
+      weight += w;
+    }
+    out.push(if weight.abs() > f32::EPSILON {
+      sum / weight
+    } else {
+      0.0
+    });
+  }
+}
 
-  low_pass_filter_kernel(data, cutoff_n, &tmp, &mut b);
+// resample 输出的样本数
+pub fn resampled_len(len: usize, sample_rate: u32, target_sample_rate: u32) -> usize {
+  if sample_rate == target_sample_rate {
+    len
+  } else if sample_rate > target_sample_rate && sample_rate.is_multiple_of(target_sample_rate) {
+    len / (sample_rate / target_sample_rate) as usize
+  } else if sample_rate < target_sample_rate {
+    (len as u64 * target_sample_rate as u64 / sample_rate as u64) as usize
+  } else {
+    let df = (sample_rate as f32) / (target_sample_rate as f32);
+    (len as f32 / df).round_ties_even() as usize
+  }
 }
 
-pub fn downsample(input: &[f32], sample_rate: u32, target_sample_rate: u32, out: &mut Vec<f32>) {
+// 降采样时整数倍直接抽取，否则（包括升采样）按 quality 线性插值或加窗 sinc 插值
+pub fn resample(
+  input: &[f32],
+  sample_rate: u32,
+  target_sample_rate: u32,
+  quality: ResampleQuality,
+  out: &mut Vec<f32>,
+) {
   out.clear();
-  if sample_rate <= target_sample_rate {
+  if sample_rate == target_sample_rate {
     out.extend_from_slice(input);
     return;
   }
 
-  if sample_rate.is_multiple_of(target_sample_rate) {
+  let out_len = resampled_len(input.len(), sample_rate, target_sample_rate);
+  if sample_rate > target_sample_rate && sample_rate.is_multiple_of(target_sample_rate) {
     let skip = (sample_rate / target_sample_rate) as usize;
-    let out_len = input.len() / skip;
     out.reserve(out_len.saturating_sub(out.capacity()));
     for i in 0..out_len {
       out.push(input[i * skip]);
@@ -72,13 +337,16 @@ pub fn downsample(input: &[f32], sample_rate: u32, target_sample_rate: u32, out:
   }
 
   let df = (sample_rate as f32) / (target_sample_rate as f32);
-  let out_len = (input.len() as f32 / df).round_ties_even() as usize;
   out.reserve(out_len.saturating_sub(out.capacity()));
+  if let ResampleQuality::High = quality {
+    resample_sinc(input, df, out_len, out);
+    return;
+  }
+  let last = input.len().saturating_sub(1);
   for j in 0..out_len {
     let f_index = df * (j as f32);
-    let i0 = f_index.floor() as usize;
-    // 感觉这里应该是i0+1，但是原始代码是这么写的
-    let i1 = i0.min(input.len().saturating_sub(1));
+    let i0 = (f_index.floor() as usize).min(last);
+    let i1 = (i0 + 1).min(last);
     let t = f_index - (i0 as f32);
     let y = input[i0] * (1.0 - t) + input[i1] * t;
     out.push(y);
@@ -91,109 +359,744 @@ pub fn pre_emphasis(data: &mut [f32], coeff: f32) {
   }
 }
 
-pub fn hamming(data: &mut [f32]) {
-  let n = data.len() as f32;
-  for (i, x) in data.iter_mut().enumerate() {
+// 生成长度为 len 的窗系数（对称窗，首尾对应 i = 0 和 i = len - 1）
+pub fn window(kind: WindowFunction, len: usize, out: &mut Vec<f32>) {
+  let n = len as f32;
+  out.clear();
+  // 长度为 1 时 n - 1 为 0，单点窗的权重取 1
+  if len <= 1 {
+    out.resize(len, 1.0);
+    return;
+  }
+  out.extend((0..len).map(|i| {
     let i = i as f32 / (n - 1.0);
-    let w = 0.54 - 0.46 * (2.0 * PI * i).cos();
+    match kind {
+      WindowFunction::Hamming => 0.54 - 0.46 * (2.0 * PI * i).cos(),
+      WindowFunction::Hann => 0.5 - 0.5 * (2.0 * PI * i).cos(),
+      WindowFunction::Blackman => 0.42 - 0.5 * (2.0 * PI * i).cos() + 0.08 * (4.0 * PI * i).cos(),
+    }
+  }));
+}
+
+pub fn apply_window(data: &mut [f32], window: &[f32]) {
+  for (x, w) in data.iter_mut().zip(window) {
     *x *= w;
   }
 }
 
-// 理论上没问题，偷个懒（
-pub fn fft(data: &[f32], complex: &mut Vec<Complex32>, out: &mut Vec<f32>) {
+// FFT 之后（幅度谱、mel 滤波、取对数、DCT）可以在 f32 或 f64 下计算，见 highPrecision 选项
+pub trait Real: FftNum + Float + FloatConst {
+  const SLANEY_F_SP: Self;
+  const SLANEY_MIN_LOG_HZ: Self;
+  const SLANEY_MIN_LOG_MEL: Self;
+  // ln(6.4) / 27
+  const SLANEY_LOG_STEP: Self;
+
+  fn round_ties_even(self) -> Self;
+
+  // 转回 f32 存储，超出范围时与 as 一样变成 inf
+  fn narrow(self) -> f32;
+
+  // 进程内共用的 planner，按长度缓存已经规划过的 FFT。compare、异步任务、rayon 任务
+  // 每次新建的缓冲池都从这里取计划，不会重新规划
+  fn planner() -> &'static Mutex<RealFftPlanner<Self>>;
+
+  fn magnitude(complex: &[Complex<Self>], out: &mut Vec<Self>) {
+    out.extend(complex.iter().map(|c| c.norm()));
+  }
+}
+
+// Slaney（Auditory Toolbox）mel 刻度：1000 Hz 以下线性，以上对数
+impl Real for f32 {
+  const SLANEY_F_SP: f32 = 200.0 / 3.0;
+  const SLANEY_MIN_LOG_HZ: f32 = 1000.0;
+  const SLANEY_MIN_LOG_MEL: f32 = Self::SLANEY_MIN_LOG_HZ / Self::SLANEY_F_SP;
+  const SLANEY_LOG_STEP: f32 = 0.068_751_78;
+
+  fn round_ties_even(self) -> f32 {
+    f32::round_ties_even(self)
+  }
+
+  fn narrow(self) -> f32 {
+    self
+  }
+
+  fn planner() -> &'static Mutex<RealFftPlanner<f32>> {
+    static PLANNER: LazyLock<Mutex<RealFftPlanner<f32>>> =
+      LazyLock::new(|| Mutex::new(RealFftPlanner::new()));
+    &PLANNER
+  }
+
+  // 8 个一组算 sqrt(re^2 + im^2)，尾部不足 8 个的走标量
+  #[cfg(feature = "simd")]
+  fn magnitude(complex: &[Complex<f32>], out: &mut Vec<f32>) {
+    use wide::f32x8;
+
+    let mut chunks = complex.chunks_exact(8);
+    for chunk in chunks.by_ref() {
+      let re = f32x8::new(std::array::from_fn(|i| chunk[i].re));
+      let im = f32x8::new(std::array::from_fn(|i| chunk[i].im));
+      out.extend_from_slice(&re.mul_add(re, im * im).sqrt().to_array());
+    }
+    out.extend(chunks.remainder().iter().map(|c| c.norm()));
+  }
+}
+
+impl Real for f64 {
+  const SLANEY_F_SP: f64 = 200.0 / 3.0;
+  const SLANEY_MIN_LOG_HZ: f64 = 1000.0;
+  const SLANEY_MIN_LOG_MEL: f64 = Self::SLANEY_MIN_LOG_HZ / Self::SLANEY_F_SP;
+  const SLANEY_LOG_STEP: f64 = 0.068_751_777_420_949_12;
+
+  fn round_ties_even(self) -> f64 {
+    f64::round_ties_even(self)
+  }
+
+  fn narrow(self) -> f32 {
+    self as f32
+  }
+
+  fn planner() -> &'static Mutex<RealFftPlanner<f64>> {
+    static PLANNER: LazyLock<Mutex<RealFftPlanner<f64>>> =
+      LazyLock::new(|| Mutex::new(RealFftPlanner::new()));
+    &PLANNER
+  }
+}
+
+// 小常数和下标转成 T，二者在 f32 / f64 下都精确
+#[inline]
+fn lit<T: Real>(x: f32) -> T {
+  T::from_f32(x).unwrap()
+}
+
+#[inline]
+fn idx<T: Real>(i: usize) -> T {
+  T::from_usize(i).unwrap()
+}
+
+// 缓存的 FFT 计划和 scratch 空间，帧长不变时每帧直接复用，长度变化时才向共用的 planner 取计划
+#[derive(Default)]
+pub struct FftPlan<T: FftNum> {
+  plan: Option<Arc<dyn RealToComplex<T>>>,
+  scratch: Vec<Complex<T>>,
+}
+
+impl<T: Real> FftPlan<T> {
+  fn process(&mut self, data: &mut [T], complex: &mut [Complex<T>]) {
+    let n = data.len();
+    let plan = match &self.plan {
+      Some(plan) if plan.len() == n => plan,
+      _ => {
+        let plan = T::planner()
+          .lock()
+          .unwrap_or_else(PoisonError::into_inner)
+          .plan_fft_forward(n);
+        self.scratch = plan.make_scratch_vec();
+        self.plan.insert(plan)
+      }
+    };
+    // 长度由 plan 和调用方保证，不会出错
+    let _ = plan.process_with_scratch(data, complex, &mut self.scratch);
+  }
+}
+
+// 实数 FFT，只算 n/2 + 1 个不重复的 bin，输出幅度；data 会被当作临时空间覆盖
+pub fn fft<T: Real>(
+  data: &mut [T],
+  plan: &mut FftPlan<T>,
+  complex: &mut Vec<Complex<T>>,
+  out: &mut Vec<T>,
+) {
   let n = data.len();
-  thread_local! {
-    static FFT_PLANNER: RefCell<FftPlanner<f32>> = RefCell::new(FftPlanner::new());
+  out.clear();
+  if n == 0 {
+    return;
   }
   complex.clear();
-  complex.reserve(n.saturating_sub(complex.capacity()));
-  for &x in data {
-    complex.push(Complex32::new(x, 0.0));
-  }
-  if n > 0 {
-    FFT_PLANNER.with(|planner_ref| {
-      let mut planner = planner_ref.borrow_mut();
-      let fft = planner.plan_fft_forward(n);
-      fft.process(complex);
-    });
+  complex.resize(n / 2 + 1, Complex::new(T::zero(), T::zero()));
+  plan.process(data, complex);
+  out.reserve(complex.len().saturating_sub(out.capacity()));
+  T::magnitude(complex, out);
+}
+
+// 频率方向降采样：把 data 均分成 bins 段，每段取平均
+pub fn pool_bins<T: Real>(data: &[T], bins: usize, out: &mut Vec<f32>) {
+  let len = data.len();
+  if len == 0 {
+    return;
+  }
+  out.reserve(bins.saturating_sub(out.capacity()));
+  for k in 0..bins {
+    let lo = (k * len / bins).min(len.saturating_sub(1));
+    let hi = ((k + 1) * len / bins).clamp(lo + 1, len);
+    let slice = &data[lo..hi];
+    let sum = slice.iter().fold(T::zero(), |acc, &x| acc + x);
+    out.push((sum / idx(slice.len())).narrow());
+  }
+}
+
+// 取对数之前的下限：退化或没有覆盖任何 bin 的 mel 通道能量为 0，不加下限会得到 -inf，
+// 整帧都会被当作非有限值丢弃。f64 下也用同一个值，两种精度的结果保持一致
+#[inline]
+fn energy_floor<T: Real>(value: T) -> T {
+  value.max(lit(f32::MIN_POSITIVE))
+}
+
+#[inline]
+pub fn power_to_db<T: Real>(array: &mut [T]) {
+  for value in array.iter_mut() {
+    *value = lit::<T>(10.0) * energy_floor(*value).log10();
   }
-  out.clear();
-  out.reserve(n.saturating_sub(out.capacity()));
-  out.extend(complex.iter().map(|c| c.norm()));
 }
 
 #[inline]
-pub fn power_to_db(array: &mut [f32]) {
+pub fn power_to_ln<T: Real>(array: &mut [T]) {
   for value in array.iter_mut() {
-    *value = 10.0 * value.log10();
+    *value = energy_floor(*value).ln();
   }
 }
 
 #[inline]
-pub fn to_mel(hz: f32, slaney: bool) -> f32 {
-  let a = if slaney { 2595.0 } else { 1127.0 };
-  a * (hz / 700.0 + 1.0).ln()
+pub fn power_to_log2<T: Real>(array: &mut [T]) {
+  for value in array.iter_mut() {
+    *value = energy_floor(*value).log2();
+  }
 }
 
+// slaney 为 false 时是 HTK 公式 1127 * ln(1 + f / 700)
 #[inline]
-pub fn to_hz(mel: f32, slaney: bool) -> f32 {
-  let a = if slaney { 2595.0 } else { 1127.0 };
-  700.0 * ((mel / a).exp() - 1.0)
+pub fn to_mel<T: Real>(hz: T, slaney: bool) -> T {
+  if slaney {
+    if hz < T::SLANEY_MIN_LOG_HZ {
+      hz / T::SLANEY_F_SP
+    } else {
+      T::SLANEY_MIN_LOG_MEL + (hz / T::SLANEY_MIN_LOG_HZ).ln() / T::SLANEY_LOG_STEP
+    }
+  } else {
+    lit::<T>(1127.0) * (hz / lit(700.0) + T::one()).ln()
+  }
 }
 
-pub fn dct(spectrum: &[f32], out: &mut [f32]) {
+#[inline]
+pub fn to_hz<T: Real>(mel: T, slaney: bool) -> T {
+  if slaney {
+    if mel < T::SLANEY_MIN_LOG_MEL {
+      mel * T::SLANEY_F_SP
+    } else {
+      T::SLANEY_MIN_LOG_HZ * ((mel - T::SLANEY_MIN_LOG_MEL) * T::SLANEY_LOG_STEP).exp()
+    }
+  } else {
+    lit::<T>(700.0) * ((mel / lit(1127.0)).exp() - T::one())
+  }
+}
+
+// DCT-II；ortho 为 true 时第 0 项乘 sqrt(1/N)、其余乘 sqrt(2/N)（与 scipy / librosa 的 norm="ortho" 相同）
+// 只计算前 num_coeffs 个系数（含 c0），各系数互相独立，结果与全量计算逐位一致
+pub fn dct<T: Real>(spectrum: &[T], out: &mut [T], num_coeffs: usize, ortho: bool) {
   let len = spectrum.len();
-  let a = PI / len as f32;
+  let count = num_coeffs.min(len);
+  let a = T::PI() / idx(len);
 
-  for (i, cep_val) in out.iter_mut().enumerate().take(len) {
-    let mut sum = 0.0;
+  for (i, cep_val) in out.iter_mut().enumerate().take(count) {
+    let mut sum = T::zero();
     for (j, spec_val) in spectrum.iter().enumerate() {
-      let ang = (j as f32 + 0.5) * i as f32 * a;
-      sum += *spec_val * ang.cos();
+      let ang = (idx::<T>(j) + lit(0.5)) * idx(i) * a;
+      sum = sum + *spec_val * ang.cos();
     }
     *cep_val = sum;
   }
+
+  if ortho && len > 0 {
+    let n = idx::<T>(len);
+    let (first, rest) = ((T::one() / n).sqrt(), (lit::<T>(2.0) / n).sqrt());
+    for (i, cep_val) in out.iter_mut().enumerate().take(count) {
+      *cep_val = *cep_val * if i == 0 { first } else { rest };
+    }
+  }
 }
 
-pub fn mel_filter_bank(spectrum: &[f32], sample_rate: f32, mel_div: usize, out: &mut [f32]) {
-  let len = spectrum.len();
+// 正弦倒谱提升：c[n] *= 1 + (L / 2) * sin(pi * n / L)
+pub fn lifter(cepstrum: &mut [f32], l: f32) {
+  for (n, c) in cepstrum.iter_mut().enumerate() {
+    *c *= 1.0 + 0.5 * l * (PI * n as f32 / l).sin();
+  }
+}
+
+// 舍入与 uLipSync C# 版 MelFilterBank 相同：Math.Round 默认是银行家舍入，所以中心点用
+// round_ties_even，起止点分别是 ceil / floor，累加区间 (iBegin, iEnd] 和顺序也一致。
+// 逐行对应的版本见 mel_filter_bank_ulipsync
+// 滤波器覆盖 range = [fmin, fmax]（Hz），默认取 [0, sample_rate / 2]
+pub fn mel_filter_bank<T: Real>(
+  spectrum: &[T],
+  sample_rate: f32,
+  mel_div: usize,
+  range: (f32, f32),
+  slaney: bool,
+  norm: MelNorm,
+  out: &mut [T],
+) {
+  // spectrum 为单边谱 [0, n/2]
+  let nyquist = lit::<T>(sample_rate) / lit(2.0);
+  let mel_min = to_mel::<T>(lit(range.0), slaney);
+  let mel_max = to_mel::<T>(lit(range.1), slaney);
+  let n_max = spectrum.len().saturating_sub(1);
+  let df = nyquist / idx(n_max);
+  let d_mel = (mel_max - mel_min) / idx(mel_div + 1);
+
+  for (n, out_val) in out.iter_mut().enumerate().take(mel_div) {
+    let mel_begin = mel_min + d_mel * idx(n);
+    let mel_center = mel_min + d_mel * idx(n + 1);
+    let mel_end = mel_min + d_mel * idx(n + 2);
+
+    let f_begin = to_hz(mel_begin, slaney);
+    let f_center = to_hz(mel_center, slaney);
+    let f_end = to_hz(mel_end, slaney);
+
+    // 相邻边界落在同一频率时三角形退化，权重会除以 0，这个通道记为 0
+    if f_center <= f_begin || f_end <= f_center {
+      *out_val = T::zero();
+      continue;
+    }
 
-  let f_max = sample_rate / 2.0;
+    // 与 as usize 一样，负数和 NaN 取 0
+    let to_index = |x: T| ToPrimitive::to_usize(&x).unwrap_or(0);
+    let i_begin = to_index((f_begin / df).ceil());
+    let i_center = to_index(Real::round_ties_even(f_center / df));
+    let i_end = to_index((f_end / df).floor());
+
+    let mut sum = T::zero();
+    for (i, spec_val) in spectrum
+      .iter()
+      .enumerate()
+      .skip(i_begin + 1)
+      .take(i_end.saturating_sub(i_begin))
+    {
+      let f = df * idx(i);
+      let mut a = if i < i_center {
+        (f - f_begin) / (f_center - f_begin)
+      } else {
+        (f_end - f) / (f_end - f_center)
+      };
+      if let MelNorm::SlaneyArea = norm {
+        a = a / ((f_end - f_begin) * lit(0.5));
+      }
+      sum = sum + a * *spec_val;
+    }
+    *out_val = sum;
+  }
+}
+
+// ulipsyncExact 模式：uLipSync C# 版 MelFilterBank 的逐行移植。只有 HTK 刻度、覆盖 [0, sample_rate / 2]、
+// 按面积归一化，运算顺序与 C# 相同（mel 边界直接取 dMel * n，不加 fmin）；三角形退化时
+// 与 C# 一样照除，不单独置 0
+pub fn mel_filter_bank_ulipsync<T: Real>(
+  spectrum: &[T],
+  sample_rate: f32,
+  mel_div: usize,
+  out: &mut [T],
+) {
+  let f_max = lit::<T>(sample_rate) / lit(2.0);
   let mel_max = to_mel(f_max, false);
-  let n_max = len / 2;
-  let df = f_max / n_max as f32;
-  let d_mel = mel_max / (mel_div + 1) as f32;
+  let n_max = spectrum.len().saturating_sub(1);
+  let df = f_max / idx(n_max);
+  let d_mel = mel_max / idx(mel_div + 1);
 
   for (n, out_val) in out.iter_mut().enumerate().take(mel_div) {
-    let mel_begin = d_mel * n as f32;
-    let mel_center = d_mel * (n + 1) as f32;
-    let mel_end = d_mel * (n + 2) as f32;
+    let mel_begin = d_mel * idx(n);
+    let mel_center = d_mel * idx(n + 1);
+    let mel_end = d_mel * idx(n + 2);
 
     let f_begin = to_hz(mel_begin, false);
     let f_center = to_hz(mel_center, false);
     let f_end = to_hz(mel_end, false);
 
-    let i_begin = (f_begin / df).ceil() as usize;
-    let i_center = (f_center / df).round_ties_even() as usize;
-    let i_end = (f_end / df).floor() as usize;
+    // C# 的 (int) 向零取整，Math.Round 为银行家舍入
+    let to_index = |x: T| ToPrimitive::to_usize(&x).unwrap_or(0);
+    let i_begin = to_index((f_begin / df).ceil());
+    let i_center = to_index(Real::round_ties_even(f_center / df));
+    let i_end = to_index((f_end / df).floor());
 
-    let mut sum = 0.0;
+    let mut sum = T::zero();
     for (i, spec_val) in spectrum
       .iter()
       .enumerate()
       .skip(i_begin + 1)
-      .take(i_end - i_begin)
+      .take(i_end.saturating_sub(i_begin))
     {
-      let f = df * i as f32;
+      let f = df * idx(i);
       let mut a = if i < i_center {
         (f - f_begin) / (f_center - f_begin)
       } else {
         (f_end - f) / (f_end - f_center)
       };
-      a /= (f_end - f_begin) * 0.5;
-      sum += a * *spec_val;
+      a = a / ((f_end - f_begin) * lit(0.5));
+      sum = sum + a * *spec_val;
     }
     *out_val = sum;
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // uLipSync Runtime/Core/Algorithm.cs 的 MelFilterBank 照源码逐行转写（不是 C# 运行的输出）：
+  // 输入为完整的 N 点幅度谱，nMax = N / 2，math.round 走 System.Math.Round（银行家舍入），(int) 向零取整
+  fn ulipsync_mel_filter_bank(spectrum: &[f32], sample_rate: f32, mel_div: usize) -> Vec<f32> {
+    let to_mel = |hz: f32| 1127.0 * (hz / 700.0 + 1.0).ln();
+    let to_hz = |mel: f32| 700.0 * ((mel / 1127.0).exp() - 1.0);
+    let f_max = sample_rate / 2.0;
+    let mel_max = to_mel(f_max);
+    let n_max = (spectrum.len() / 2) as i32;
+    let df = f_max / n_max as f32;
+    let d_mel = mel_max / (mel_div + 1) as f32;
+    let mut mel_spectrum = vec![0.0; mel_div];
+    for (n, mel) in mel_spectrum.iter_mut().enumerate() {
+      let mel_begin = d_mel * n as f32;
+      let mel_center = d_mel * (n + 1) as f32;
+      let mel_end = d_mel * (n + 2) as f32;
+      let f_begin = to_hz(mel_begin);
+      let f_center = to_hz(mel_center);
+      let f_end = to_hz(mel_end);
+      let i_begin = (f_begin / df).ceil() as i32;
+      let i_center = (f_center / df).round_ties_even() as i32;
+      let i_end = (f_end / df).floor() as i32;
+      let mut sum = 0.0f32;
+      for i in (i_begin + 1)..=i_end {
+        let f = df * i as f32;
+        let mut a = if i < i_center {
+          (f - f_begin) / (f_center - f_begin)
+        } else {
+          (f_end - f) / (f_end - f_center)
+        };
+        a /= (f_end - f_begin) * 0.5;
+        sum += a * spectrum[i as usize];
+      }
+      *mel = sum;
+    }
+    mel_spectrum
+  }
+
+  #[test]
+  fn mel_filter_bank_matches_ulipsync() {
+    for (n, sample_rate, mel_div) in [(1024, 16000.0, 30), (512, 16000.0, 26), (2048, 44100.0, 40)]
+    {
+      // 完整谱按共轭对称构造，单边谱取前 n / 2 + 1 个
+      let full: Vec<f32> = (0..n)
+        .map(|i| {
+          let k = i.min(n - i) as f32;
+          1.0 + (k * 0.37).sin().abs() * 10.0 / (1.0 + k * 0.01)
+        })
+        .collect();
+      let expected = ulipsync_mel_filter_bank(&full, sample_rate, mel_div);
+      let mut out = vec![0.0; mel_div];
+      mel_filter_bank(
+        &full[..n / 2 + 1],
+        sample_rate,
+        mel_div,
+        (0.0, sample_rate / 2.0),
+        false,
+        MelNorm::SlaneyArea,
+        &mut out,
+      );
+      assert_eq!(out, expected, "n = {n}, sample rate = {sample_rate}");
+      let mut exact = vec![0.0; mel_div];
+      mel_filter_bank_ulipsync(&full[..n / 2 + 1], sample_rate, mel_div, &mut exact);
+      assert_eq!(exact, expected, "n = {n}, sample rate = {sample_rate}");
+    }
+  }
+
+  #[test]
+  fn energy_segments_ignore_silence() {
+    let silence = vec![0.0; 16000];
+    assert!(energy_segments(&silence, 320, -30.0, -40.0, -60.0, 3).is_empty());
+
+    // 0.25 s 静音 + 0.5 s 440 Hz + 0.25 s 静音
+    let mut take = vec![0.0; 4000];
+    take.extend((0..8000).map(|i| 0.5 * (2.0 * PI * 440.0 * i as f32 / 16000.0).sin()));
+    take.extend(vec![0.0; 4000]);
+    assert_eq!(
+      energy_segments(&take, 320, -30.0, -40.0, -60.0, 3),
+      vec![(3840, 12160)]
+    );
+  }
+
+  #[test]
+  fn pool_bins_on_empty_spectrum() {
+    let mut out = Vec::new();
+    pool_bins::<f32>(&[], 8, &mut out);
+    assert!(out.is_empty());
+    pool_bins(&[1.0f32, 3.0, 5.0, 7.0], 2, &mut out);
+    assert_eq!(out, vec![2.0, 6.0]);
+  }
+
+  #[test]
+  fn log_bases_differ_by_constant_factors() {
+    let mel = [1e-4f32, 0.01, 0.5, 1.0, 2.0, 1000.0];
+    let (mut db, mut ln, mut log2) = (mel, mel, mel);
+    power_to_db(&mut db);
+    power_to_ln(&mut ln);
+    power_to_log2(&mut log2);
+    for i in 0..mel.len() {
+      // 10 * log10(x) = ln(x) * 10 / ln(10)，log2(x) = ln(x) / ln(2)
+      assert!((db[i] - ln[i] * 10.0 / std::f32::consts::LN_10).abs() < 1e-4);
+      assert!((log2[i] - ln[i] / std::f32::consts::LN_2).abs() < 1e-4);
+    }
+    assert_eq!(db[3], 0.0);
+    assert!((db[5] - 30.0).abs() < 1e-5);
+  }
+
+  fn ramp(len: usize) -> Vec<f32> {
+    (0..len).map(|i| i as f32).collect()
+  }
+
+  #[test]
+  fn linear_resample_interpolates_ramp() {
+    let input = ramp(441);
+    let mut out = Vec::new();
+    resample(&input, 44100, 16000, ResampleQuality::Low, &mut out);
+    assert_eq!(out.len(), 160);
+    let df = 44100.0 / 16000.0;
+    for (j, &y) in out.iter().enumerate() {
+      let x = df * j as f32;
+      // 斜坡上的线性插值落在两个相邻样本之间，并且就等于位置本身
+      assert!(x.floor() <= y && y <= x.ceil(), "out[{j}] = {y}");
+      assert!((y - x).abs() < 1e-3, "out[{j}] = {y}, expected {x}");
+    }
+  }
+
+  #[test]
+  fn linear_upsample_length_and_endpoints() {
+    let input = ramp(100);
+    let mut out = Vec::new();
+    for (rate, target, len) in [(8000, 16000, 200), (10000, 15000, 150)] {
+      resample(&input, rate, target, ResampleQuality::Low, &mut out);
+      assert_eq!(out.len(), len, "{rate} -> {target}");
+      assert_eq!(out[0], 0.0);
+      // 最后几个输出超过最后一个输入的位置，停在最后一个样本上
+      assert_eq!(*out.last().unwrap(), 99.0);
+      assert!(out.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+    resample(&input, 8000, 16000, ResampleQuality::Low, &mut out);
+    assert_eq!(&out[..5], &[0.0, 0.5, 1.0, 1.5, 2.0]);
+  }
+
+  #[test]
+  fn hann_window_endpoints_are_zero() {
+    let mut w = Vec::new();
+    window(WindowFunction::Hann, 512, &mut w);
+    assert_eq!(w.len(), 512);
+    assert!(w[0].abs() < 1e-7);
+    assert!(w[511].abs() < 1e-7);
+    assert!(w.iter().all(|&x| (0.0..=1.0).contains(&x)));
+  }
+
+  #[test]
+  fn short_windows_stay_finite() {
+    for kind in [
+      WindowFunction::Hamming,
+      WindowFunction::Hann,
+      WindowFunction::Blackman,
+    ] {
+      let mut w = Vec::new();
+      window(kind, 1, &mut w);
+      assert_eq!(w, [1.0]);
+      window(kind, 2, &mut w);
+      assert_eq!(w.len(), 2);
+      assert!(w.iter().all(|x| x.is_finite()));
+      let mut data = [0.5, -0.5];
+      apply_window(&mut data, &w);
+      assert!(data.iter().all(|x| x.is_finite()));
+    }
+  }
+
+  #[test]
+  fn degenerate_mel_bands_stay_finite() {
+    // 16 kHz、128 点窗口只有 65 个 bin，26 个通道里低频的几个三角形退化或不覆盖任何 bin
+    let spectrum: Vec<f32> = (0..65).map(|i| 1.0 + i as f32 * 0.1).collect();
+    for natural_log in [false, true] {
+      let mut mel = vec![0.0; 26];
+      mel_filter_bank(
+        &spectrum,
+        16000.0,
+        26,
+        (0.0, 8000.0),
+        false,
+        MelNorm::SlaneyArea,
+        &mut mel,
+      );
+      assert!(mel.contains(&0.0));
+      if natural_log {
+        power_to_ln(&mut mel);
+      } else {
+        power_to_db(&mut mel);
+      }
+      assert!(mel.iter().all(|v| v.is_finite()), "{mel:?}");
+    }
+  }
+
+  #[test]
+  fn lifter_scales_by_sine_weights() {
+    let cepstrum = [2.0f32, -1.0, 0.5, 3.0, -4.0];
+    let mut lifted = cepstrum;
+    lifter(&mut lifted, 22.0);
+    // c0 的权重为 1，c_n 乘以 1 + 11 * sin(pi * n / 22)
+    assert_eq!(lifted[0], cepstrum[0]);
+    for n in 1..cepstrum.len() {
+      let weight = 1.0 + 11.0 * (PI * n as f32 / 22.0).sin();
+      assert!((lifted[n] - cepstrum[n] * weight).abs() < 1e-5);
+    }
+    assert!((lifted[1] / cepstrum[1] - 2.5654).abs() < 1e-3);
+  }
+
+  #[test]
+  fn ortho_dct_scaling() {
+    let spectrum = [1.0f32, 2.0, 3.0, 4.0];
+    let (mut plain, mut ortho) = ([0.0; 4], [0.0; 4]);
+    dct(&spectrum, &mut plain, 4, false);
+    dct(&spectrum, &mut ortho, 4, true);
+    // 与 scipy.fft.dct([1, 2, 3, 4], norm="ortho") 相同
+    let expected = [5.0, -2.230_442_5, 0.0, -0.158_512_6];
+    for i in 0..4 {
+      assert!((ortho[i] - expected[i]).abs() < 1e-5, "{ortho:?}");
+    }
+    // c0 乘 sqrt(1/4)，其余乘 sqrt(2/4)
+    assert!((ortho[0] - plain[0] * 0.5).abs() < 1e-6);
+    assert!((ortho[1] - plain[1] * std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+  }
+
+  #[test]
+  fn rms_normalization_ignores_a_spike() {
+    let mut signal: Vec<f32> = (0..1000).map(|i| 0.1 * (i as f32 * 0.05).sin()).collect();
+    signal[500] = 1.0;
+    let (mut by_peak, mut by_rms) = (signal.clone(), signal.clone());
+    normalize(&mut by_peak, 1.0);
+    normalize_rms(&mut by_rms, std::f32::consts::FRAC_1_SQRT_2);
+    // 峰值归一化由尖峰决定增益，其余部分几乎不变；RMS 归一化让主体接近满幅正弦
+    assert_eq!(by_peak[500], 1.0);
+    let body = |data: &[f32]| data.iter().take(400).fold(0.0f32, |m, x| m.max(x.abs()));
+    assert!(body(&by_peak) < 0.11);
+    assert!(body(&by_rms) > 0.6, "{}", body(&by_rms));
+    assert!((rms(&by_rms) - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4);
+  }
+
+  #[test]
+  fn remove_dc_clears_bin_zero() {
+    let mut data: Vec<f32> = (0..256)
+      .map(|i| 0.3 + 0.5 * (2.0 * PI * 8.0 * i as f32 / 256.0).sin())
+      .collect();
+    let spectrum = |data: &[f32]| {
+      let (mut plan, mut complex, mut out) = (FftPlan::default(), Vec::new(), Vec::new());
+      fft(&mut data.to_vec(), &mut plan, &mut complex, &mut out);
+      out
+    };
+    assert!((spectrum(&data)[0] - 0.3 * 256.0).abs() < 1e-2);
+    remove_dc(&mut data);
+    let after = spectrum(&data);
+    assert!(after[0] < 1e-3, "{}", after[0]);
+    // 正弦所在的 bin 8 不受影响
+    assert!((after[8] - 0.5 * 128.0).abs() < 1e-2);
+  }
+
+  #[test]
+  fn low_pass_kernel_is_never_empty() {
+    // 过渡带比采样率还宽时算出的核长原本为 0
+    assert_eq!(low_pass_filter_len(100.0, 500.0), MIN_LPF_KERNEL_LEN);
+    let mut data: Vec<f32> = (0..64).map(|i| (i as f32 * 0.3).sin()).collect();
+    let (mut tmp, mut b) = (Vec::new(), Vec::new());
+    low_pass_filter(&mut data, 1200.0, 600.0, 500.0, &mut tmp, &mut b);
+    assert!(data.iter().all(|v| v.is_finite()));
+  }
+
+  #[test]
+  fn sinc_resample_leaks_less_than_linear() {
+    // 11–20 kHz 的扫频全部高于 16 kHz 的奈奎斯特频率，理想的降采样输出应当接近静音
+    let (rate, len) = (44100.0, 4410);
+    let duration = len as f32 / rate;
+    let (f0, f1) = (11000.0, 20000.0);
+    let sweep: Vec<f32> = (0..len)
+      .map(|i| {
+        let t = i as f32 / rate;
+        (2.0 * PI * (f0 * t + (f1 - f0) * t * t / (2.0 * duration))).sin()
+      })
+      .collect();
+    let energy = |quality| {
+      let mut out = Vec::new();
+      resample(&sweep, 44100, 16000, quality, &mut out);
+      // 两端的核不完整，只看中间部分
+      let inner = &out[32..out.len() - 32];
+      inner.iter().map(|v| v * v).sum::<f32>() / inner.len() as f32
+    };
+    let linear = energy(ResampleQuality::Low);
+    let sinc = energy(ResampleQuality::High);
+    assert!(linear > 0.1, "linear {linear}");
+    assert!(sinc < linear * 1e-3, "sinc {sinc} vs linear {linear}");
+  }
+
+  #[test]
+  fn fft_plans_are_shared_between_pools() {
+    // 各自新建的计划缓存拿到的是共用 planner 里同一个计划，不会重新规划
+    let (mut a, mut b) = (FftPlan::<f32>::default(), FftPlan::default());
+    let (mut complex, mut out) = (Vec::new(), Vec::new());
+    fft(&mut [1.0; 48], &mut a, &mut complex, &mut out);
+    fft(&mut [1.0; 48], &mut b, &mut complex, &mut out);
+    assert!(Arc::ptr_eq(
+      a.plan.as_ref().unwrap(),
+      b.plan.as_ref().unwrap()
+    ));
+    assert_eq!(out[0], 48.0);
+  }
+
+  #[test]
+  fn high_pass_attenuates_rumble() {
+    // 80 Hz 截止的一阶高通：20 Hz 的隆隆声衰减约 12 dB，1 kHz 基本不变
+    let rms_after = |freq: f32| {
+      let mut data: Vec<f32> = (0..16000)
+        .map(|i| (2.0 * PI * freq * i as f32 / 16000.0).sin())
+        .collect();
+      high_pass_filter(&mut data, 16000.0, 80.0);
+      // 跳过开头的暂态
+      let tail = &data[8000..];
+      (tail.iter().map(|v| v * v).sum::<f32>() / tail.len() as f32).sqrt()
+    };
+    let input = 0.5f32.sqrt();
+    let rumble = 20.0 * (rms_after(20.0) / input).log10();
+    let voice = 20.0 * (rms_after(1000.0) / input).log10();
+    assert!((-13.0..-11.0).contains(&rumble), "rumble {rumble} dB");
+    assert!(voice > -0.5, "voice {voice} dB");
+  }
+
+  #[test]
+  fn truncated_dct_matches_full_bitwise() {
+    let spectrum: Vec<f32> = (0..26)
+      .map(|i| -40.0 + (i as f32 * 0.7).sin() * 12.0)
+      .collect();
+    for ortho in [false, true] {
+      let (mut full, mut part) = (vec![0.0; 26], vec![0.0; 13]);
+      dct(&spectrum, &mut full, spectrum.len(), ortho);
+      dct(&spectrum, &mut part, 13, ortho);
+      let bits = |v: &[f32]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+      assert_eq!(bits(&part[..13]), bits(&full[..13]), "ortho = {ortho}");
+    }
+  }
+}
+
+#[cfg(all(test, feature = "simd"))]
+mod simd_tests {
+  use super::*;
+
+  #[test]
+  fn simd_magnitude_matches_scalar() {
+    // 37 个 bin：4 组 8 个走 SIMD，剩下 5 个走标量
+    let complex: Vec<Complex<f32>> = (0..37)
+      .map(|i| Complex::new((i as f32 * 0.7).sin() * 3.0, (i as f32 * 1.3).cos() - 0.5))
+      .collect();
+    let mut simd = Vec::new();
+    <f32 as Real>::magnitude(&complex, &mut simd);
+    assert_eq!(simd.len(), complex.len());
+    for (c, m) in complex.iter().zip(&simd) {
+      let scalar = c.norm();
+      assert!((m - scalar).abs() <= scalar * 1e-6, "{m} vs {scalar}");
+    }
+  }
+}
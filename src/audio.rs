@@ -0,0 +1,160 @@
+use crate::algorithm::{self, Agc};
+use crate::mfcc::{self, MfccBufferPool, MfccConfig};
+use crate::StereoMode;
+use std::borrow::Cow;
+use std::f32::consts::FRAC_1_SQRT_2;
+
+// 交错多声道输入，按帧读取
+pub enum AudioSource<'a> {
+  Mono(Cow<'a, [f32]>),
+  // 每帧挑能量更大的那个声道
+  Louder {
+    data: Cow<'a, [f32]>,
+    channels: usize,
+  },
+}
+
+impl<'a> AudioSource<'a> {
+  pub fn new(data: &'a [f32], channels: usize, mode: StereoMode) -> Self {
+    if channels <= 1 {
+      return AudioSource::Mono(Cow::Borrowed(data));
+    }
+    let mono = match mode {
+      StereoMode::Louder => {
+        return AudioSource::Louder {
+          data: Cow::Borrowed(data),
+          channels,
+        }
+      }
+      StereoMode::Downmix => downmix(data, channels),
+      StereoMode::Left => extract_channel(data, channels, 0),
+      StereoMode::Right => extract_channel(data, channels, 1),
+    };
+    AudioSource::Mono(Cow::Owned(mono))
+  }
+
+  pub fn len(&self) -> usize {
+    match self {
+      AudioSource::Mono(data) => data.len(),
+      AudioSource::Louder { data, channels } => data.len() / channels,
+    }
+  }
+
+  // 整段做 AGC，借用的数据会先复制一份
+  pub fn apply_agc(&mut self, sample_rate: u32, params: &Agc) {
+    match self {
+      AudioSource::Mono(data) => algorithm::agc(data.to_mut(), 1, sample_rate, params),
+      AudioSource::Louder { data, channels } => {
+        algorithm::agc(data.to_mut(), *channels, sample_rate, params)
+      }
+    }
+  }
+
+  // 读取 [start - warmup, start + out.len() - warmup)，声道只按 start 之后的分析窗比较能量
+  pub fn read(&self, start: usize, warmup: usize, out: &mut [f32]) {
+    let from = start - warmup;
+    match self {
+      AudioSource::Mono(data) => out.copy_from_slice(&data[from..from + out.len()]),
+      AudioSource::Louder { data, channels } => {
+        let end = from + out.len();
+        let channel = (0..*channels)
+          .map(|c| {
+            let energy: f32 = (start..end).map(|i| data[i * channels + c].powi(2)).sum();
+            (c, energy)
+          })
+          .fold((0, f32::NEG_INFINITY), |best, cur| {
+            if cur.1 > best.1 {
+              cur
+            } else {
+              best
+            }
+          })
+          .0;
+        for (i, x) in out.iter_mut().enumerate() {
+          *x = data[(from + i) * channels + channel];
+        }
+      }
+    }
+  }
+}
+
+// 并行 / 异步提取时每帧的结果
+pub enum FrameOutcome {
+  Features(Vec<f32>),
+  NonFinite,
+  Silent,
+}
+
+// frame 为带预热样本的整帧，只看分析窗部分
+pub fn is_silent(silence_threshold: Option<f32>, frame: &[f32], warmup: usize) -> bool {
+  silence_threshold.is_some_and(|threshold| algorithm::rms(&frame[warmup..]) < threshold)
+}
+
+// 不经过生成器读取并提取一帧，供 rayon 线程和 addSampleAsync 的后台线程使用
+#[allow(clippy::too_many_arguments)]
+pub fn extract_outcome(
+  config: &MfccConfig,
+  silence_threshold: Option<f32>,
+  source: &AudioSource,
+  start: usize,
+  warmup: usize,
+  input_sample_rate: u32,
+  pool: &mut MfccBufferPool,
+  frame_buf: &mut [f32],
+) -> FrameOutcome {
+  source.read(start, warmup, frame_buf);
+  if is_silent(silence_threshold, frame_buf, warmup) {
+    return FrameOutcome::Silent;
+  }
+  let mut out = Vec::new();
+  mfcc::extract_mfcc(frame_buf, warmup, input_sample_rate, config, pool, &mut out);
+  if out.iter().all(|v| v.is_finite()) {
+    FrameOutcome::Features(out)
+  } else {
+    FrameOutcome::NonFinite
+  }
+}
+
+// 5.1（L R C LFE Ls Rs）按 ITU-R BS.775 的系数：中置和环绕 -3 dB，LFE 丢弃
+const DOWNMIX_5_1: [f32; 6] = [1.0, 1.0, FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2, FRAC_1_SQRT_2];
+
+// 按权重之和归一化，满幅输入不会削波；立体声及其他声道数为各声道平均
+fn downmix(data: &[f32], channels: usize) -> Vec<f32> {
+  let weights: Vec<f32> = if channels == DOWNMIX_5_1.len() {
+    DOWNMIX_5_1.to_vec()
+  } else {
+    vec![1.0; channels]
+  };
+  let scale = 1.0 / weights.iter().sum::<f32>();
+  data
+    .chunks_exact(channels)
+    .map(|frame| frame.iter().zip(&weights).map(|(x, w)| x * w).sum::<f32>() * scale)
+    .collect()
+}
+
+// 把 NaN / inf 样本换成 0，返回处理后的数据和替换的样本数；没有需要替换的样本时不复制
+pub fn sanitize(data: &[f32]) -> (Cow<'_, [f32]>, usize) {
+  let count = data.iter().filter(|x| !x.is_finite()).count();
+  if count == 0 {
+    return (Cow::Borrowed(data), 0);
+  }
+  let cleaned = data
+    .iter()
+    .map(|&x| if x.is_finite() { x } else { 0.0 })
+    .collect();
+  (Cow::Owned(cleaned), count)
+}
+
+// 16 位 PCM 转到 [-1, 1)
+pub fn pcm16_to_f32(data: &[i16]) -> Vec<f32> {
+  data.iter().map(|&x| x as f32 / 32768.0).collect()
+}
+
+fn extract_channel(data: &[f32], channels: usize, channel: usize) -> Vec<f32> {
+  data
+    .iter()
+    .skip(channel)
+    .step_by(channels)
+    .copied()
+    .collect()
+}
@@ -0,0 +1,105 @@
+use crate::CompareMethod;
+
+// 分母加上 epsilon：零向量与任何向量的余弦相似度为 0（正交）
+pub const DEFAULT_COSINE_EPSILON: f32 = 1e-12;
+
+pub fn cosine_similarity(a: &[f32], b: &[f32], epsilon: f32) -> f32 {
+  let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+  for (&x, &y) in a.iter().zip(b) {
+    dot += x * y;
+    norm_a += x * x;
+    norm_b += y * y;
+  }
+  dot / (norm_a.sqrt() * norm_b.sqrt() + epsilon)
+}
+
+// 各自减去均值后的余弦相似度
+pub fn pearson_correlation(a: &[f32], b: &[f32], epsilon: f32) -> f32 {
+  let center = |v: &[f32]| -> Vec<f32> {
+    let mean = v.iter().sum::<f32>() / v.len().max(1) as f32;
+    v.iter().map(|x| x - mean).collect()
+  };
+  cosine_similarity(&center(a), &center(b), epsilon)
+}
+
+// 越小越相近；余弦相似度和相关系数换算成 1 - similarity。
+// weights 不为空时两个向量先逐项乘以权重再比较，向量比权重长（带差分）时权重循环使用
+pub fn distance(
+  method: &CompareMethod,
+  epsilon: f32,
+  weights: Option<&[f32]>,
+  a: &[f32],
+  b: &[f32],
+) -> f32 {
+  match weights {
+    None => unweighted(method, epsilon, a, b),
+    Some(weights) => {
+      let scale = |v: &[f32]| -> Vec<f32> {
+        v.iter()
+          .zip(weights.iter().cycle())
+          .map(|(x, w)| x * w)
+          .collect()
+      };
+      unweighted(method, epsilon, &scale(a), &scale(b))
+    }
+  }
+}
+
+fn unweighted(method: &CompareMethod, epsilon: f32, a: &[f32], b: &[f32]) -> f32 {
+  match method {
+    CompareMethod::L1Norm => a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum(),
+    CompareMethod::L2Norm => a
+      .iter()
+      .zip(b)
+      .map(|(x, y)| (x - y) * (x - y))
+      .sum::<f32>()
+      .sqrt(),
+    CompareMethod::CosineSimilarity => 1.0 - cosine_similarity(a, b, epsilon),
+    CompareMethod::PearsonCorrelation => 1.0 - pearson_correlation(a, b, epsilon),
+    CompareMethod::ChebyshevNorm => a
+      .iter()
+      .zip(b)
+      .map(|(x, y)| (x - y).abs())
+      .fold(0.0, f32::max),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn zero_frame_is_orthogonal() {
+    let zero = [0.0; 12];
+    let frame: Vec<f32> = (1..=12).map(|i| i as f32).collect();
+    let similarity = cosine_similarity(&zero, &frame, DEFAULT_COSINE_EPSILON);
+    assert_eq!(similarity, 0.0);
+    assert_eq!(cosine_similarity(&zero, &zero, DEFAULT_COSINE_EPSILON), 0.0);
+    assert!((cosine_similarity(&frame, &frame, DEFAULT_COSINE_EPSILON) - 1.0).abs() < 1e-6);
+  }
+
+  #[test]
+  fn pearson_maps_correlation_to_distance() {
+    let method = CompareMethod::PearsonCorrelation;
+    let frame: Vec<f32> = (1..=12).map(|i| (i as f32).sin()).collect();
+    // 整体偏移和缩放（响度变化）不影响相关系数
+    let louder: Vec<f32> = frame.iter().map(|x| 3.0 * x + 5.0).collect();
+    let inverted: Vec<f32> = frame.iter().map(|x| -x).collect();
+    let d = |b: &[f32]| distance(&method, DEFAULT_COSINE_EPSILON, None, &frame, b);
+    assert!(d(&louder).abs() < 1e-5);
+    assert!((d(&inverted) - 2.0).abs() < 1e-5);
+    let cosine = distance(
+      &CompareMethod::CosineSimilarity,
+      DEFAULT_COSINE_EPSILON,
+      None,
+      &frame,
+      &louder,
+    );
+    assert!(cosine > 0.1);
+    assert_eq!(method.as_u32(), 4);
+    assert!(matches!(
+      CompareMethod::from_u32(4),
+      Some(CompareMethod::PearsonCorrelation)
+    ));
+  }
+}
@@ -1,30 +1,68 @@
 #![deny(clippy::all)]
 
+use audio::{AudioSource, FrameOutcome};
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
 use napi_derive::napi;
-use serde::Serialize;
-use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Write};
 
-mod algorithm;
-mod mfcc;
+// benches/ 需要直接调用 DSP 函数
+#[doc(hidden)]
+pub mod algorithm;
+mod audio;
+mod distance;
+mod linalg;
+#[doc(hidden)]
+pub mod mfcc;
+#[cfg(feature = "png")]
+mod spectrogram;
+mod stats;
+#[cfg(feature = "wav")]
+mod wav;
 
+// 默认的 MFCC 系数个数
 const MFCC_SIZE: usize = 12;
+// 特征提取流程（同样输入得到的系数）发生变化时递增
+const PIPELINE_VERSION: u32 = 4;
+// 输出 profile 的结构（字段及其含义）发生变化时递增；没有这个字段的旧 profile 视为 0
+const FORMAT_VERSION: u32 = 4;
+// mfccDataCount 的默认上限，超过需要显式设置 allowLargeCapacity
+const MAX_MFCC_DATA_COUNT: u32 = 100_000;
+// trimSilenceDb 裁剪首尾静音时两边保留的余量（毫秒）
+const TRIM_SILENCE_MARGIN_MS: usize = 10;
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 struct MfccCalibrationData {
   array: Vec<f32>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct MfccEntry {
   name: String,
   #[serde(rename = "mfccCalibrationDataList")]
   mfcc_calibration_data_list: Vec<MfccCalibrationData>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  confidence: Option<f32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  trajectory: Option<Vec<f32>>,
 }
 
 #[derive(Serialize)]
+struct FrameRecord<'a> {
+  phoneme: &'a str,
+  array: &'a [f32],
+}
+
+#[derive(Serialize, Deserialize)]
 struct OutputJson {
+  #[serde(rename = "formatVersion", default)]
+  format_version: u32,
   #[serde(rename = "mfccNum")]
   mfcc_num: usize,
   #[serde(rename = "mfccDataCount")]
@@ -39,15 +77,32 @@ struct OutputJson {
   use_standardization: u32,
   #[serde(rename = "compareMethod")]
   compare_method: u32,
+  #[serde(
+    rename = "coefficientWeights",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  coefficient_weights: Option<Vec<f32>>,
+  // 为 true 时每帧后半段是差分，fromJson 据此只读回前半段
+  #[serde(rename = "computeDeltas", default, skip_serializing_if = "is_false")]
+  compute_deltas: bool,
+  // 为 true 时每帧第一个系数是 c0，fromJson 据此恢复 keepC0
+  #[serde(rename = "keepC0", default, skip_serializing_if = "is_false")]
+  keep_c0: bool,
   #[serde(rename = "mfccs")]
   mfccs: Vec<MfccEntry>,
 }
 
 #[napi]
+#[derive(Clone, Copy)]
 pub enum CompareMethod {
   L1Norm,
   L2Norm,
   CosineSimilarity,
+  ChebyshevNorm,
+  /// 两个向量各自减去均值后的余弦相似度（皮尔逊相关系数），不受整体电平偏移影响。
+  /// uLipSync 本身不支持，写进 profile 后只能由自己的匹配代码读取。
+  PearsonCorrelation,
 }
 
 impl CompareMethod {
@@ -56,126 +111,3233 @@ impl CompareMethod {
       CompareMethod::L1Norm => 0,
       CompareMethod::L2Norm => 1,
       CompareMethod::CosineSimilarity => 2,
+      CompareMethod::ChebyshevNorm => 3,
+      CompareMethod::PearsonCorrelation => 4,
+    }
+  }
+
+  fn from_u32(value: u32) -> Option<Self> {
+    match value {
+      0 => Some(CompareMethod::L1Norm),
+      1 => Some(CompareMethod::L2Norm),
+      2 => Some(CompareMethod::CosineSimilarity),
+      3 => Some(CompareMethod::ChebyshevNorm),
+      4 => Some(CompareMethod::PearsonCorrelation),
+      _ => None,
+    }
+  }
+
+  // 相似度类的方法，compare 的分数越高越相近
+  fn higher_is_better(&self) -> bool {
+    matches!(
+      self,
+      CompareMethod::CosineSimilarity | CompareMethod::PearsonCorrelation
+    )
+  }
+}
+
+fn check_sample_rate(input_sample_rate: u32) -> Result<()> {
+  if input_sample_rate == 0 {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "Input sample rate must be greater than 0",
+    ));
+  }
+  Ok(())
+}
+
+// 一帧重采样之后为空时后面的 FFT 没有数据，直接报错而不是输出空帧
+fn check_resampled_len(
+  window_len: usize,
+  input_sample_rate: u32,
+  target_sample_rate: u32,
+) -> Result<()> {
+  if algorithm::resampled_len(window_len, input_sample_rate, target_sample_rate) == 0 {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "A {window_len}-sample window at {input_sample_rate} Hz resamples to 0 samples at {target_sample_rate} Hz"
+      ),
+    ));
+  }
+  Ok(())
+}
+
+// 解析 profile JSON，拒绝比当前版本新的 formatVersion（字段含义可能已经变了）
+fn parse_profile(json: &str) -> Result<OutputJson> {
+  let profile: OutputJson = serde_json::from_str(json)
+    .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid profile: {e}")))?;
+  if profile.format_version > FORMAT_VERSION {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "Unsupported profile formatVersion {} (this build supports up to {FORMAT_VERSION})",
+        profile.format_version
+      ),
+    ));
+  }
+  Ok(profile)
+}
+
+// 抗混叠低通的截止频率是输入和目标采样率中较低者的一半，过渡带不比它窄时滤波器无法设计
+fn check_lpf_range(input_sample_rate: u32, target_sample_rate: u32, range: f32) -> Result<()> {
+  let cutoff = input_sample_rate.min(target_sample_rate) as f32 / 2.0;
+  if cutoff <= range {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "lpfTransitionRange ({range} Hz) must be smaller than the anti-aliasing cutoff of {cutoff} Hz at {input_sample_rate} Hz input"
+      ),
+    ));
+  }
+  Ok(())
+}
+
+// 去掉首尾空白，空名字会让 uLipSync 读取 profile 失败。不做大小写转换
+fn normalize_phoneme_name(name: String) -> Result<String> {
+  let trimmed = name.trim();
+  if trimmed.is_empty() {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "Phoneme name must not be empty",
+    ));
+  }
+  Ok(if trimmed.len() == name.len() {
+    name
+  } else {
+    trimmed.to_string()
+  })
+}
+
+fn check_channels(audio_len: usize, channels: Option<u32>) -> Result<usize> {
+  let channels = channels.unwrap_or(1) as usize;
+  if channels == 0 {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "Channel count must be at least 1",
+    ));
+  }
+  if !audio_len.is_multiple_of(channels) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("Audio length {audio_len} is not a multiple of {channels} channels"),
+    ));
+  }
+  Ok(channels)
+}
+
+fn parse_pipeline_order(order: &[Stage]) -> Result<[Stage; 3]> {
+  let stages: [Stage; 3] = order.try_into().map_err(|_| {
+    Error::new(
+      Status::InvalidArg,
+      format!(
+        "pipelineOrder must contain exactly 3 stages, got {}",
+        order.len()
+      ),
+    )
+  })?;
+  if mfcc::DEFAULT_STAGES
+    .iter()
+    .any(|stage| !stages.contains(stage))
+  {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "pipelineOrder must contain PreEmphasis, Window and Normalize exactly once",
+    ));
+  }
+  Ok(stages)
+}
+
+// f32 按最短表示转成 f64，0.97 读回来仍是 0.97 而不是 0.9700000286
+fn widen(value: f32) -> f64 {
+  value.to_string().parse().unwrap_or(value as f64)
+}
+
+fn is_false(value: &bool) -> bool {
+  !value
+}
+
+fn round_significant(value: f32, digits: u32) -> f32 {
+  if value == 0.0 || !value.is_finite() {
+    return value;
+  }
+  let magnitude = (value.abs() as f64).log10().floor() as i32;
+  let factor = 10f64.powi(digits as i32 - 1 - magnitude);
+  ((value as f64 * factor).round() / factor) as f32
+}
+
+fn frame_hash(array: &[f32]) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  for value in array {
+    value.to_bits().hash(&mut hasher);
+  }
+  hasher.finish()
+}
+
+// 裁剪到 cap 帧，返回被丢掉的帧
+fn trim_frames(
+  list: &mut Vec<MfccCalibrationData>,
+  cap: usize,
+  policy: RetentionPolicy,
+) -> Vec<MfccCalibrationData> {
+  if list.len() <= cap {
+    return Vec::new();
+  }
+  match policy {
+    RetentionPolicy::Unlimited => Vec::new(),
+    RetentionPolicy::Fifo => {
+      let overflow = list.len() - cap;
+      list.drain(0..overflow).collect()
+    }
+    RetentionPolicy::Representative => {
+      let keep = stats::representative(list, cap);
+      let mut dropped = Vec::new();
+      let mut kept = Vec::with_capacity(keep.len());
+      let mut keep = keep.into_iter().peekable();
+      for (i, data) in std::mem::take(list).into_iter().enumerate() {
+        if keep.next_if_eq(&i).is_some() {
+          kept.push(data);
+        } else {
+          dropped.push(data);
+        }
+      }
+      *list = kept;
+      dropped
     }
   }
 }
 
 #[napi]
-pub struct ProfileGenerator {
+#[derive(Clone, Copy)]
+pub enum FeatureType {
+  Mfcc,
+  Spectrum,
+}
+
+#[napi]
+#[derive(Clone, Copy)]
+pub enum LogBase {
+  Db10,
+  Ln,
+  Log2,
+}
+
+#[napi]
+#[derive(Clone, Copy)]
+pub enum MelNorm {
+  None,
+  SlaneyArea,
+}
+
+#[napi]
+#[derive(Clone, Copy)]
+pub enum NormalizeMode {
+  Peak,
+  Rms,
+}
+
+#[napi]
+#[derive(Clone, Copy)]
+pub enum ResampleQuality {
+  Low,
+  High,
+}
+
+#[napi]
+#[derive(Clone, Copy)]
+pub enum RetentionPolicy {
+  Fifo,
+  Representative,
+  Unlimited,
+}
+
+#[napi]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stage {
+  PreEmphasis,
+  Window,
+  Normalize,
+}
+
+#[napi]
+#[derive(Clone, Copy)]
+pub enum StereoMode {
+  Downmix,
+  Louder,
+  Left,
+  Right,
+}
+
+#[napi]
+#[derive(Clone, Copy, PartialEq)]
+pub enum WindowFunction {
+  Hamming,
+  Hann,
+  Blackman,
+}
+
+#[napi(object)]
+pub struct BuildInfo {
+  pub msgpack: bool,
+  pub png: bool,
+  pub simd: bool,
+  pub rayon: bool,
+  pub wav: bool,
+  pub pipeline_version: u32,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SampleReport {
+  /// 成功提取出特征的帧数
+  pub frames_processed: u32,
+  /// 特征含 NaN / inf 被跳过的帧数（通常是输入削波或全静音）
+  pub frames_dropped_nonfinite: u32,
+  /// 末尾凑不满一帧而没有处理的窗口数（streaming 模式下这部分会留到下一次，记为 0）
+  pub frames_dropped_short: u32,
+  /// 低于 `silenceThresholdDb` 被当作静音跳过的帧数
+  pub frames_skipped_silence: u32,
+  /// `sanitizeInput` 开启时被替换成 0 的 NaN / inf 输入样本数
+  pub samples_sanitized: u32,
+  /// `addSamples` 中因为为空而跳过的录音数
+  pub buffers_skipped_empty: u32,
+  /// 该音素之前的录音用的是另一个采样率时，为之前（第一次）的采样率；一致时不设置。
+  /// 开启 `strictSampleRate` 时这种情况直接报错
+  pub expected_sample_rate: Option<u32>,
+}
+
+#[napi(object)]
+pub struct PhonemeScore {
+  pub name: String,
+  /// `compareMethod` 为 `CosineSimilarity` / `PearsonCorrelation` 时是相似度，否则是距离
+  pub score: f64,
+  /// 为 true 时分数越高越相近（余弦相似度、相关系数），否则越低越相近
+  pub higher_is_better: bool,
+}
+
+#[napi(object)]
+pub struct PhonemeStats {
+  pub count: u32,
+  pub mean: Vec<f64>,
+  pub variance: Vec<f64>,
+}
+
+/// 当前二进制编译进了哪些可选 feature，用来在 JS 侧提前判断方法是否可用。
+#[napi]
+pub fn build_info() -> BuildInfo {
+  BuildInfo {
+    msgpack: cfg!(feature = "msgpack"),
+    png: cfg!(feature = "png"),
+    simd: cfg!(feature = "simd"),
+    rayon: cfg!(feature = "rayon"),
+    wav: cfg!(feature = "wav"),
+    pipeline_version: PIPELINE_VERSION,
+  }
+}
+
+#[napi(object)]
+pub struct Segment {
+  pub start_sample: u32,
+  pub end_sample: u32,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SegmentOptions {
+  /// 计算 RMS 的帧长（毫秒），默认 20。
+  pub frame_ms: Option<f64>,
+  /// 相对最响帧高于这个电平（dB，<= 0）时开始一段，默认 -30。
+  pub on_db: Option<f64>,
+  /// 相对最响帧低于这个电平（dB，不高于 `onDb`）时结束一段，默认 -40。
+  pub off_db: Option<f64>,
+  /// 绝对电平下限（dBFS，<= 0），默认 -60。两个相对门限都不会低于它，
+  /// 整段录音都低于它（例如全静音）时不返回任何段。
+  pub floor_db: Option<f64>,
+  /// 短于这个时长（毫秒）的段丢弃，按帧数向上取整，默认 60。
+  pub min_duration_ms: Option<f64>,
+}
+
+/// 按能量把一段连续录音切成若干有声区间（`[startSample, endSample)`）。
+/// 默认以 20ms 为一帧计算 RMS，相对最响帧高于 -30dB 开始、低于 -40dB 结束，
+/// 绝对电平低于 -60 dBFS 的帧不算有声，短于 60ms 的段丢弃；各项可以通过 `options` 调整。
+#[napi]
+pub fn segment_by_energy(
+  audio: Float32Array,
+  input_sample_rate: u32,
+  options: Option<SegmentOptions>,
+) -> Result<Vec<Segment>> {
+  if audio.is_empty() {
+    return Err(Error::new(Status::InvalidArg, "Audio data is empty"));
+  }
+  check_sample_rate(input_sample_rate)?;
+
+  let opts = options.unwrap_or_default();
+  let frame_ms = opts.frame_ms.unwrap_or(20.0);
+  let on_db = opts.on_db.unwrap_or(-30.0);
+  let off_db = opts.off_db.unwrap_or(-40.0);
+  let floor_db = opts.floor_db.unwrap_or(-60.0);
+  let min_duration_ms = opts.min_duration_ms.unwrap_or(60.0);
+  if !(frame_ms.is_finite() && frame_ms > 0.0) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("frameMs must be a positive number, got {frame_ms}"),
+    ));
+  }
+  if !(on_db.is_finite() && on_db <= 0.0 && off_db.is_finite() && off_db <= on_db) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("onDb and offDb must satisfy offDb <= onDb <= 0, got onDb {on_db}, offDb {off_db}"),
+    ));
+  }
+  if !(floor_db.is_finite() && floor_db <= 0.0) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("floorDb must be a finite number <= 0, got {floor_db}"),
+    ));
+  }
+  if !(min_duration_ms.is_finite() && min_duration_ms >= 0.0) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("minDurationMs must be a non-negative number, got {min_duration_ms}"),
+    ));
+  }
+
+  let frame_len = ((input_sample_rate as f64 * frame_ms / 1000.0) as usize).max(1);
+  let min_frames = (min_duration_ms / frame_ms).ceil() as usize;
+  let segments = algorithm::energy_segments(
+    &audio,
+    frame_len,
+    on_db as f32,
+    off_db as f32,
+    floor_db as f32,
+    min_frames,
+  )
+  .into_iter()
+  .map(|(start, end)| Segment {
+    start_sample: start as u32,
+    end_sample: end as u32,
+  })
+  .collect();
+  Ok(segments)
+}
+
+/// 对一个窗口直接跑一遍特征提取（与 `addSample` 的单帧处理相同，使用默认选项），
+/// 不需要创建 `ProfileGenerator`。整个 `audio` 作为一帧，不做切分。
+#[napi]
+pub fn extract_features(
+  audio: Float32Array,
+  input_sample_rate: u32,
   target_sample_rate: u32,
-  mel_filter_bank_channels: usize,
+  mel_filter_bank_channels: u32,
+) -> Result<Float32Array> {
+  if audio.is_empty() {
+    return Err(Error::new(Status::InvalidArg, "Audio data is empty"));
+  }
+  check_sample_rate(input_sample_rate)?;
+  if target_sample_rate == 0 || mel_filter_bank_channels == 0 {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "targetSampleRate and melFilterBankChannels must be greater than 0",
+    ));
+  }
+  let config = mfcc::MfccConfig::new(target_sample_rate, mel_filter_bank_channels as usize);
+  check_lpf_range(input_sample_rate, target_sample_rate, config.lpf_range)?;
+  check_resampled_len(audio.len(), input_sample_rate, target_sample_rate)?;
+
+  let mut frame = audio.to_vec();
+  let mut out = Vec::new();
+  mfcc::extract_mfcc(
+    &mut frame,
+    0,
+    input_sample_rate,
+    &config,
+    &mut mfcc::MfccBufferPool::new(),
+    &mut out,
+  );
+  if out.iter().any(|v| !v.is_finite()) {
+    return Err(Error::new(
+      Status::GenericFailure,
+      "Extracted features contain non-finite values",
+    ));
+  }
+  Ok(Float32Array::new(out))
+}
+
+#[napi]
+pub struct ProfileGenerator {
+  config: mfcc::MfccConfig,
+  // 各阶段的中间缓冲，整段录音复用
+  pool: mfcc::MfccBufferPool,
   compare_method: CompareMethod,
+  coefficient_weights: Option<Vec<f32>>,
+  cosine_epsilon: f32,
   entries: HashMap<String, Vec<MfccCalibrationData>>,
   mfcc_data_count: usize,
   sample_count: usize,
+  hop_size: usize,
+  // 构造时发现的不影响使用的问题，见 getWarnings
+  warnings: Vec<String>,
   use_standardization: bool,
+  defer_trim: bool,
+  retention: RetentionPolicy,
+  discard_filter_transient: bool,
+  stereo_mode: StereoMode,
+  float_precision: Option<u32>,
+  cmn: bool,
+  cmn_before_trim: bool,
+  cmvn: bool,
+  cmn_sums: HashMap<String, stats::RunningMean>,
+  // 每个音素第一次 addSample 的输入采样率
+  sample_rates: HashMap<String, u32>,
+  strict_sample_rate: bool,
+  include_confidence: bool,
+  include_trajectory: bool,
+  agc: Option<algorithm::Agc>,
+  reject_exact_duplicates: bool,
+  dedup_threshold: Option<f32>,
+  frame_hashes: HashMap<String, HashMap<u64, u32>>,
+  streaming: bool,
+  pad_short_input: bool,
+  pretty: bool,
+  compute_deltas: bool,
+  progress_interval: u32,
+  sanitize_input: bool,
+  #[cfg(feature = "rayon")]
+  parallel: bool,
+  pending: HashMap<String, PendingAudio>,
+  silence_threshold_db: Option<f64>,
+  trim_silence_db: Option<f64>,
+}
+
+// 生成器要能整个移到其他线程（worker pool、异步任务）使用，字段里不能出现 Rc / RefCell 之类。
+// 缓冲池里的 FFT 计划是 Arc，共用的 planner 是 algorithm 里加锁的 static，都不影响这里
+const _: fn() = || {
+  fn assert_send<T: Send>() {}
+  assert_send::<ProfileGenerator>();
+};
+
+// Reference 只在主线程上创建、使用和释放（resolve 与 AsyncWork 的回收都在主线程），
+// 后台线程的 compute 不会碰它
+struct GeneratorRef(Reference<ProfileGenerator>);
+
+unsafe impl Send for GeneratorRef {}
+
+/// `addSampleAsync` 的后台任务：在 libuv 线程池里按调用时的配置快照提取特征，
+/// 完成后回到主线程把帧写入生成器。
+pub struct AddSampleTask {
+  generator: GeneratorRef,
+  audio: Vec<f32>,
+  channels: usize,
+  phoneme_name: String,
+  input_sample_rate: u32,
+  config: mfcc::MfccConfig,
+  stereo_mode: StereoMode,
+  agc: Option<algorithm::Agc>,
+  silence_threshold: Option<f32>,
+  start: usize,
+  warmup: usize,
+  sample_count: usize,
+  hop_size: usize,
+  progress: Option<ThreadsafeFunction<u32, (), u32, Status, false>>,
+  progress_interval: u32,
+  sanitized: u32,
+  expected_sample_rate: Option<u32>,
+}
+
+impl Task for AddSampleTask {
+  type Output = (Vec<FrameOutcome>, u32);
+  type JsValue = SampleReport;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let mut source = AudioSource::new(&self.audio, self.channels, self.stereo_mode);
+    if let Some(params) = &self.agc {
+      source.apply_agc(self.input_sample_rate, params);
+    }
+    let total = source.len();
+    let mut pool = mfcc::MfccBufferPool::new();
+    let mut frame_buf = vec![0.0; self.warmup + self.sample_count];
+    let mut outcomes = Vec::new();
+    let mut start = self.start;
+    let mut chunks: u32 = 0;
+    while start + self.sample_count <= total {
+      outcomes.push(audio::extract_outcome(
+        &self.config,
+        self.silence_threshold,
+        &source,
+        start,
+        self.warmup,
+        self.input_sample_rate,
+        &mut pool,
+        &mut frame_buf,
+      ));
+      start += self.hop_size;
+      chunks += 1;
+      if let Some(progress) = &self.progress {
+        if chunks.is_multiple_of(self.progress_interval) {
+          progress.call(chunks, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+      }
+    }
+    let dropped_short = total.saturating_sub(start).div_ceil(self.hop_size) as u32;
+    Ok((outcomes, dropped_short))
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    let (outcomes, frames_dropped_short) = output;
+    let generator = &mut *self.generator.0;
+    let mut report = SampleReport {
+      frames_dropped_short,
+      samples_sanitized: self.sanitized,
+      expected_sample_rate: self.expected_sample_rate,
+      ..Default::default()
+    };
+    // 创建任务时已经记录过；期间 finish / reset 清空了记录时按这次的采样率重新记
+    generator.record_sample_rate(&self.phoneme_name, self.input_sample_rate);
+    for outcome in outcomes {
+      match outcome {
+        FrameOutcome::Features(data) => {
+          generator.store_frame(&self.phoneme_name, data);
+          report.frames_processed += 1;
+        }
+        FrameOutcome::NonFinite => report.frames_dropped_nonfinite += 1,
+        FrameOutcome::Silent => report.frames_skipped_silence += 1,
+      }
+    }
+    Ok(report)
+  }
+}
+
+// streaming 模式下某个音素还没用完的输入（交错多声道原始数据）
+struct PendingAudio {
+  data: Vec<f32>,
+  // 下一帧在 data 中的起点（按样本帧计）
+  start: usize,
+  channels: usize,
+  input_sample_rate: u32,
 }
 
 #[napi(object)]
+#[derive(Default)]
 pub struct ProfileGeneratorOptions {
   pub target_sample_rate: u32,
   pub mel_filter_bank_channels: u32,
+  /// 每帧保留的 MFCC 系数个数，默认 12。第 0 项被跳过，所以最多为 `melFilterBankChannels - 1`。
+  pub mfcc_num: Option<u32>,
   pub compare_method: Option<CompareMethod>,
+  /// 距离计算前每个系数乘上的权重，长度必须等于每帧的系数个数（`mfccNum`，`keepC0` 时加 1；
+  /// `Spectrum` 特征要设置 `spectrumBins` 并与之相等），权重不能为负。低阶系数给更大的权重
+  /// 可以让 `compare` 更看重包络的大致形状。同样用于 `includeTrajectory`、`includeConfidence`
+  /// 和 `dedupThreshold`，开启 `computeDeltas` 后差分部分沿用对应系数的权重。会写进 profile，
+  /// `fromJson` 读回后保留。默认不加权。
+  pub coefficient_weights: Option<Vec<f64>>,
+  /// 余弦相似度（和相关系数）分母上加的小量，默认 1e-12。全零帧（例如 CMN 之后）与任何帧的相似度为 0。
+  pub cosine_epsilon: Option<f64>,
+  /// 每个音素最多保留的帧数，默认 16。超过 100000 会报错，防止误设过大的值导致内存
+  /// 随录音长度无限增长；确实需要时设置 `allowLargeCapacity`。
   pub mfcc_data_count: Option<u32>,
   pub sample_count: Option<u32>,
+  /// 相邻两帧起点的间隔（样本数），默认等于 `sampleCount`（不重叠）。取 `sampleCount / 2`
+  /// 即 50% 重叠，跨帧边界的短音素更容易得到完整的帧。末尾不足一帧的部分仍然丢弃。
+  pub hop_size: Option<u32>,
+  /// 为 true 时 `finish` 对每个音素逐系数做标准化（减去该音素所有帧的均值再除以标准差），
+  /// 标准差接近 0 的系数只减均值。输出里的 `useStandardization` 同时置 1。
+  /// 注意 `fromJson` 读入的帧已经标准化过，继续追加后会再做一次。
   pub use_standardization: Option<bool>,
+  /// 超过 `mfccDataCount` 时保留哪些帧。默认 `Fifo`：丢掉最旧的帧，只保留最近的部分。
+  /// `Representative`：用 k-means（k = `mfccDataCount`）把该音素的帧聚类，每类保留离中心最近的一帧
+  /// （仍按时间顺序排列），更均匀地覆盖整段录音里的变化；帧数达到两倍上限时裁剪一次，
+  /// `finish` 时再裁剪到上限。`Unlimited`：保留所有帧，忽略 `mfccDataCount`，输出里的
+  /// `mfccDataCount` 为帧数最多的音素的帧数；内存随录音长度线性增长。
+  pub retention_policy: Option<RetentionPolicy>,
+  /// 为 true 时 `addSample` 保留所有帧，到 `finish` 时才统一裁剪到 `mfccDataCount`。
+  /// 裁剪结果与调用顺序无关，但在 `finish` 之前内存会随录音长度线性增长。
+  pub defer_trim: Option<bool>,
+  /// 为 true 时每帧都带上前面 `blen - 1` 个真实样本一起做低通（`blen` 为 FIR 核长，约
+  /// `3.1 * inputSampleRate / 500`），这样保留下来的帧不含滤波器瞬态；前面凑不够这段预热的
+  /// 帧（起点小于 `blen - 1` 的帧）会被丢弃。
+  pub discard_filter_transient: Option<bool>,
+  /// 多声道输入（`channels > 1`）的处理方式，默认 `Downmix`：立体声取平均，5.1（L R C LFE Ls Rs）
+  /// 按 ITU-R BS.775 加权（中置、环绕 -3 dB，忽略 LFE），结果按权重之和归一化，不会削波。
+  /// `Louder` 对每帧分别比较各声道能量，只用能量最大的那个声道提取特征。
+  pub stereo_mode: Option<StereoMode>,
+  /// `finish` 时把每个系数舍入到指定的有效数字位数（1..=9），缩短 JSON 文本。
+  /// MFCC 的数值范围大约是几十，取 4 位时误差在 0.01 量级，对匹配结果基本没有影响；
+  /// 不设置时保持 f32 的完整精度。
+  pub float_precision: Option<u32>,
+  /// 为 true 时 `finish` 对每个音素做倒谱均值归一化（减去该音素所有帧的均值向量）。
+  pub cmn: Option<bool>,
+  /// 为 true 时 `finish` 做全局倒谱均值方差归一化（CMVN）：用所有音素全部保留帧的逐系数
+  /// 均值和标准差，对每一帧减均值、除以标准差（标准差接近 0 的系数只减均值），用来去掉
+  /// 麦克风等信道的影响。在逐音素的 `cmn` / `useStandardization` 之后进行。
+  /// **输出的系数不再是原始 MFCC**，匹配时输入也需要按同样的统计量归一化。
+  pub cmvn: Option<bool>,
+  /// CMN 均值的统计范围。默认 false：只用裁剪到 `mfccDataCount` 之后保留下来的帧；
+  /// true：用裁剪之前该音素出现过的所有帧（包括已被丢弃的旧帧）。
+  pub cmn_before_trim: Option<bool>,
+  /// 存储的特征类型，默认 `Mfcc`。`Spectrum` 直接保存单边幅度谱（跳过 mel/dB/DCT），
+  /// 此时输出的 `mfccNum` 为谱长度。
+  pub feature_type: Option<FeatureType>,
+  /// `Spectrum` 模式下把单边谱按频率均分取平均，压缩到固定的 bin 数。不设置时保留全部
+  /// `n/2 + 1` 个 bin，长度取决于重采样后的窗长，同一个 profile 应使用相同的输入采样率。
+  pub spectrum_bins: Option<u32>,
+  /// 为 true 时 `finish` 给每个音素输出 `confidence`（0..1）：取各帧到该音素中心的平均距离
+  /// （按 `compareMethod`）的倒数，再除以所有音素中的最大值。帧越集中越接近 1，
+  /// 少于 2 帧的音素为 0。
+  pub include_confidence: Option<bool>,
+  /// 为 true 时 `finish` 给每个音素输出 `trajectory`：按保存顺序相邻两帧之间的距离
+  /// （按 `compareMethod`），长度为帧数 - 1。曲线尖峰多说明录音不稳定。
+  pub include_trajectory: Option<bool>,
+  /// 为 true 时在分帧之前对整段输入做自动增益（AGC），缓慢地把响度拉向 `agcTargetRms`，
+  /// 适合同一段录音里音量忽大忽小的情况，默认关闭。
+  /// 之后每帧的 `Normalize` 仍会把该帧缩放到峰值 1，所以 AGC 不改变帧与帧之间的整体电平，
+  /// 作用在于平滑帧内的音量起伏、让 `Louder` 的声道选择不受整体音量影响；
+  /// 如果 `pipelineOrder` 把 `Normalize` 放在最前面，AGC 的效果也会被它覆盖一部分。
+  pub agc: Option<bool>,
+  /// AGC 的目标 RMS，默认 0.1。
+  pub agc_target_rms: Option<f64>,
+  /// AGC 包络上升时间（毫秒），默认 5。
+  pub agc_attack_ms: Option<f64>,
+  /// AGC 包络下降时间（毫秒），默认 200。
+  pub agc_release_ms: Option<f64>,
+  /// mel 能量取对数的方式，默认 `Db10`（`10 * log10`，与之前的输出一致）。
+  /// `Ln` / `Log2` 与 `Db10` 只差常数倍，DCT 是线性的，所以 MFCC 也只是整体缩放。
+  pub log_base: Option<LogBase>,
+  /// 为 true 时，与该音素已有帧逐位相同的新帧会被跳过（每个音素维护一份帧哈希），
+  /// 重复导入同一批数据不会产生重复帧。
+  pub reject_exact_duplicates: Option<bool>,
+  /// 设置后，新帧与该音素最近保存的一帧的距离（按 `compareMethod`；`CosineSimilarity` /
+  /// `PearsonCorrelation` 时用 `1 - 相似度`）小于这个值就跳过，长时间保持同一个音时不会让几乎相同的帧占满 `mfccDataCount`。
+  /// 与 `rejectExactDuplicates` 不同，只和上一帧比较。
+  pub dedup_threshold: Option<f64>,
+  /// 降采样之后、FFT 之前三个步骤的执行顺序，必须恰好包含 `PreEmphasis`、`Window`、
+  /// `Normalize` 各一次。默认 `[PreEmphasis, Window, Normalize]`，用于复刻其他工具的特征定义。
+  pub pipeline_order: Option<Vec<Stage>>,
+  /// 为 true 时 mel 滤波器组改用 Slaney 刻度（1000 Hz 以下线性、以上对数，同 librosa 的默认值），
+  /// 默认 false 为 HTK 公式（与之前的输出一致）。
+  pub use_slaney_mel: Option<bool>,
+  /// mel 滤波器组覆盖的最低频率（Hz），默认 0。语音一般取 80 左右，避免把滤波器浪费在低频噪声上。
+  pub mel_fmin: Option<f64>,
+  /// mel 滤波器组覆盖的最高频率（Hz），默认 `targetSampleRate / 2`，不能超过它，且要大于 `melFmin`。
+  pub mel_fmax: Option<f64>,
+  /// mel 三角滤波器的归一化方式。默认 `SlaneyArea`：每个滤波器乘以 `2 / (fEnd - fBegin)`，
+  /// 面积与带宽无关（也就是之前一直使用的方式，与 librosa 的 `norm="slaney"` 相同）；
+  /// `None` 不归一化，所有三角形峰值为 1。
+  pub mel_norm: Option<MelNorm>,
+  /// 为 true 时 mel 滤波器组改用与 uLipSync C# 版 `MelFilterBank` 逐行对应的实现：起止点
+  /// `ceil` / `floor`、中心点银行家舍入、累加区间和顺序都与 C# 相同，全程 f32。只支持 uLipSync
+  /// 自己的设置，不能与 `useSlaneyMel`、`melFmin`、`melFmax`、`melNorm: None`、`highPrecision` 同时使用。
+  /// 默认 false；默认设置下两种实现的输出相同，只有三角形退化时（通道数相对 FFT 长度过多）
+  /// 这里与 C# 一样照除，不把该通道置 0。
+  pub ulipsync_exact: Option<bool>,
+  /// `Normalize` 阶段的方式，默认 `Peak`（缩放到峰值 1，与之前一致）。`Rms` 把整帧的 RMS
+  /// 缩放到满幅正弦的 RMS（`1 / sqrt(2)`），单个削波样本或爆音不会压低整帧的电平。
+  pub normalize_mode: Option<NormalizeMode>,
+  /// `Normalize` 阶段的目标峰值，默认 1（`Rms` 模式下目标为该值乘以 `1 / sqrt(2)`）。
+  /// 设为 0 跳过归一化，保留帧与帧之间的相对响度，这时 `keepC0` 输出的 c0 才能当作能量使用。
+  pub normalize_peak: Option<f64>,
+  /// 为 true 时在 FFT 之前减去每帧的均值，去掉部分麦克风带来的直流偏置，默认 false。
+  pub remove_dc: Option<bool>,
+  /// 设置后在重采样之后、预加重之前做一阶高通（截止频率 Hz，必须小于 `targetSampleRate / 2`），
+  /// 压低空调、手持麦克风等低频噪声对前几个 mel 通道的影响，语音一般取 80 左右。默认不做。
+  pub highpass_cutoff: Option<f64>,
+  /// 重采样之后给每帧叠加的三角分布（TPDF）抖动的最大幅度（满幅为 1），默认 0 不加。
+  /// 由 8 位等低位深音源转换来的录音有台阶状的量化误差，会在频谱上形成虚假的谐波线；
+  /// 加一点抖动可以让这些谐波线淹没在平坦的噪声里，代价是噪声底略微抬高。8 位音源取 `1 / 128`（1 LSB）左右。
+  /// 随机数的种子由每帧的样本值决定，同样的输入每次运行的结果相同。
+  pub dither_amount: Option<f64>,
+  /// 分析窗，默认 `Hamming`。
+  pub window: Option<WindowFunction>,
+  /// 预加重系数 `y[n] = x[n] - a * x[n - 1]`，取值 [0, 1)，默认 0.97。为 0 时信号保持不变。
+  pub pre_emphasis_coeff: Option<f64>,
+  /// 正弦倒谱提升（liftering）参数 L：第 n 个倒谱系数乘以 `1 + (L / 2) * sin(pi * n / L)`，
+  /// 用来平衡高阶系数的幅度，常用值 22。不设置或为 0 时不做。
+  pub lifter: Option<f64>,
+  /// 为 true 时 DCT 使用正交归一化（第 0 项乘 `sqrt(1/N)`，其余乘 `sqrt(2/N)`，N 为 mel 通道数），
+  /// 与 librosa 等的 `norm="ortho"` 一致。默认 false，系数与之前相同。
+  pub dct_ortho: Option<bool>,
+  /// 重采样前抗混叠低通的过渡带宽（Hz），默认 500。截止频率为目标采样率和输入采样率中
+  /// 较低者的一半，必须大于这个值：构造时按 `targetSampleRate / 2` 检查，输入采样率在
+  /// `addSample` 等调用时检查，不满足时报错。过渡带越窄高频保留得越多，但 FIR 核越长、越慢。
+  pub lpf_transition_range: Option<f64>,
+  /// 非整数倍重采样的方式。默认 `Low` 为线性插值（与之前一致）；`High` 改用 Kaiser 窗 sinc 插值，
+  /// 降采样时截止频率跟着降到目标奈奎斯特频率，减少高频混叠到上面几个 mel 通道，计算量大一些。
+  /// 采样率相同或整数倍降采样时不受影响。
+  pub resample_quality: Option<ResampleQuality>,
+  /// 为 true 时 FFT、mel 滤波、取对数和 DCT 改用 f64 计算，只在输出时转回 f32，
+  /// 与双精度的参考实现（例如 numpy / librosa）对比时误差更小。结果与默认的 f32 略有差别，
+  /// 速度稍慢。默认 false。
+  pub high_precision: Option<bool>,
+  /// 为 true 时 FFT 之前把重采样后的窗口补零到下一个 2 的幂。重采样会改变窗口长度，
+  /// 即使 `sampleCount` 是 2 的幂，送进 FFT 的长度也不一定是，这时 FFT 会慢很多。
+  /// 补零不增加信息，只让频谱更密，mel 滤波器随之调整；系数与不补零时略有差别。
+  /// `Spectrum` 特征未设置 `spectrumBins` 时每帧长度也随之变化。默认 false。
+  pub pad_fft_to_pow2: Option<bool>,
+  /// 为 true 时在每帧最前面保留第 0 个倒谱系数（帧的对数能量，有助于区分清音和浊音），
+  /// 每帧长度和输出的 `mfccNum` 都变为 `mfccNum + 1`。默认 false，与 uLipSync 的 profile 一致。
+  /// 开启时 profile 里记录 `keepC0`，`fromJson` 读回后继续保留 c0。
+  pub keep_c0: Option<bool>,
+  /// 为 true 时不检查 `mfccDataCount` 的上限。
+  pub allow_large_capacity: Option<bool>,
+  /// 为 true 时 `addSample` 会把每个音素末尾不足一帧的样本留下来，拼到下一次同一音素的输入前面，
+  /// 适合把录音切成任意长度的小块连续送入。录音结束时调用 `flushPending` 处理剩下的部分；
+  /// `finish` / `reset` 会直接丢弃未处理的样本。两次调用的声道数和采样率必须一致。
+  pub streaming: Option<bool>,
+  /// 为 true 时，短到一帧都凑不出来的输入（不足 `sampleCount`，开启 `discardFilterTransient`
+  /// 时还要加上预热长度）会补零到一帧再处理。默认 false：这样的输入不产生任何帧，
+  /// `addSample` 也不会报错。streaming 模式下短输入会留到下一次，不受此选项影响。
+  pub pad_short_input: Option<bool>,
+  /// 为 true 时先把输入里的 NaN / inf 样本替换成 0（计入 `samplesSanitized`），
+  /// 避免个别解码错误的样本让整帧特征变成非有限值而被丢弃。默认 false。
+  pub sanitize_input: Option<bool>,
+  /// 为 true 时，同一音素后续录音的采样率与第一次不同就报错。默认 false，只在返回的
+  /// `SampleReport.expectedSampleRate` 里提示。不同采样率重采样后的特征会有细微差别。
+  pub strict_sample_rate: Option<bool>,
+  /// `addSample` / `addSampleAsync` 的 `onProgress` 回调间隔（窗口数），默认 256。
+  pub progress_interval: Option<u32>,
+  /// 为 true 时 `addSample` 把各帧分到多个线程并行提取，结果仍按时间顺序入库，输出与串行一致。
+  /// 适合一次送入很长的录音；需要编译时开启 `rayon` feature，否则构造时报错。
+  pub parallel: Option<bool>,
+  /// 为 true 时 `finish` / `finishToFile` 输出带缩进的 JSON，便于阅读和 diff。
+  /// 默认 false，输出紧凑格式（与之前一致）。
+  pub pretty: Option<bool>,
+  /// 为 true 时 `finish` 在每帧后面拼上一阶差分（相邻保留帧的中心差分 `(c[t+1] - c[t-1]) / 2`，
+  /// 首尾帧用单边差分），输出的 `mfccNum` 翻倍。**生成的 profile 与普通 uLipSync 不兼容**，
+  /// 只在下游也按这个布局匹配时开启。差分按帧的保存顺序计算，多次 `addSample` 的帧会首尾相接。
+  /// 与 `cmn` 同时开启时只对系数部分减均值，差分保持不变。profile 里会记录 `computeDeltas`，
+  /// `fromJson` 据此恢复。
+  pub compute_deltas: Option<bool>,
+  /// 静音门限（dBFS，满幅为 0 dB，必须 <= 0）。分析窗内原始样本（AGC 之后、预加重和归一化之前）
+  /// 的 RMS 低于门限时跳过该帧，计入 `framesSkippedSilence`。录音里字与字之间的空白
+  /// 归一化后会变成近似随机的特征，一般取 -50 左右。不设置时不做门限。
+  pub silence_threshold_db: Option<f64>,
+  /// 首尾静音门限（dBFS，必须 <= 0）。分帧之前先去掉整段录音开头和结尾幅度一直低于门限的部分
+  /// （两边各留 10 ms），只裁两端，中间的停顿保留。不设置时不裁剪；不支持 `streaming` 模式。
+  pub trim_silence_db: Option<f64>,
 }
 
-#[napi]
 impl ProfileGenerator {
-  #[napi(constructor)]
-  pub fn new(opts: ProfileGeneratorOptions) -> Self {
-    Self {
-      target_sample_rate: opts.target_sample_rate,
-      mel_filter_bank_channels: opts.mel_filter_bank_channels as usize,
-      compare_method: opts.compare_method.unwrap_or(CompareMethod::L2Norm),
-      entries: HashMap::new(),
-      mfcc_data_count: opts.mfcc_data_count.unwrap_or(16) as usize,
-      sample_count: opts.sample_count.unwrap_or(1024) as usize,
-      use_standardization: opts.use_standardization.unwrap_or(false),
+  // 每帧特征向量的长度，即输出里的 mfccNum
+  fn feature_len(&self) -> usize {
+    match self.config.feature_type {
+      FeatureType::Mfcc => self.config.mfcc_num + usize::from(self.config.keep_c0),
+      FeatureType::Spectrum => self.config.spectrum_bins.unwrap_or_else(|| {
+        self
+          .entries
+          .values()
+          .flatten()
+          .next()
+          .map_or(0, |data| data.array.len())
+      }),
     }
   }
 
-  #[napi]
-  pub fn add_sample(
+  fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+    distance::distance(
+      &self.compare_method,
+      self.cosine_epsilon,
+      self.coefficient_weights.as_deref(),
+      a,
+      b,
+    )
+  }
+
+  // finish 系列共用：取走所有帧，做完收尾处理后组装输出，并清空各音素的附属状态
+  fn take_output(&mut self) -> OutputJson {
+    let mfcc_num = if self.compute_deltas {
+      self.feature_len() * 2
+    } else {
+      self.feature_len()
+    };
+    // 按音素名排序，同样的输入得到逐字节相同的输出
+    let mut entries: Vec<_> = std::mem::take(&mut self.entries).into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mfcc_data_count = match self.retention {
+      RetentionPolicy::Unlimited => entries
+        .iter()
+        .map(|(_, list)| list.len())
+        .max()
+        .unwrap_or(0),
+      _ => self.mfcc_data_count,
+    };
+    let mut mfcc_entries: Vec<MfccEntry> = self
+      .finalize_entries(entries)
+      .into_iter()
+      .map(|(name, data_list)| {
+        let trajectory = self.include_trajectory.then(|| {
+          data_list
+            .windows(2)
+            .map(|pair| self.distance(&pair[0].array, &pair[1].array))
+            .collect()
+        });
+        MfccEntry {
+          name,
+          mfcc_calibration_data_list: data_list,
+          confidence: None,
+          trajectory,
+        }
+      })
+      .collect();
+    self.cmn_sums.clear();
+    self.frame_hashes.clear();
+    self.pending.clear();
+    self.sample_rates.clear();
+    if self.include_confidence {
+      self.fill_confidence(&mut mfcc_entries);
+    }
+
+    OutputJson {
+      format_version: FORMAT_VERSION,
+      mfcc_num,
+      mfcc_data_count,
+      mel_filter_bank_channels: self.config.mel_filter_bank_channels,
+      target_sample_rate: self.config.target_sample_rate,
+      sample_count: self.sample_count,
+      use_standardization: if self.use_standardization { 1 } else { 0 },
+      compare_method: self.compare_method.as_u32(),
+      coefficient_weights: self.coefficient_weights.clone(),
+      compute_deltas: self.compute_deltas,
+      keep_c0: self.config.keep_c0 && matches!(self.config.feature_type, FeatureType::Mfcc),
+      mfccs: mfcc_entries,
+    }
+  }
+
+  fn fill_confidence(&self, entries: &mut [MfccEntry]) {
+    let inverse: Vec<f32> = entries
+      .iter()
+      .map(|entry| {
+        let frames = &entry.mfcc_calibration_data_list;
+        if frames.len() < 2 {
+          return 0.0;
+        }
+        let centroid: Vec<f32> = stats::mean(frames).iter().map(|&m| m as f32).collect();
+        let spread = frames
+          .iter()
+          .map(|data| self.distance(&data.array, &centroid))
+          .sum::<f32>()
+          / frames.len() as f32;
+        1.0 / spread.max(f32::EPSILON)
+      })
+      .collect();
+    let max = inverse.iter().copied().fold(0.0, f32::max);
+    for (entry, inv) in entries.iter_mut().zip(inverse) {
+      entry.confidence = Some(if max > 0.0 { inv / max } else { 0.0 });
+    }
+  }
+
+  // finish 输出前的处理：逐音素 裁剪 -> CMN -> 差分 -> 标准化，再对所有音素做全局 CMVN，
+  // 最后精度舍入
+  fn finalize_entries(
+    &self,
+    mut entries: Vec<(String, Vec<MfccCalibrationData>)>,
+  ) -> Vec<(String, Vec<MfccCalibrationData>)> {
+    for (name, data_list) in entries.iter_mut() {
+      self.finalize_frames(name, data_list);
+    }
+    if self.cmvn {
+      let mut frames: Vec<&mut MfccCalibrationData> = entries
+        .iter_mut()
+        .flat_map(|(_, data_list)| data_list.iter_mut())
+        .collect();
+      stats::cmvn(&mut frames);
+    }
+    if let Some(digits) = self.float_precision {
+      for data in entries
+        .iter_mut()
+        .flat_map(|(_, data_list)| data_list.iter_mut())
+      {
+        for value in data.array.iter_mut() {
+          *value = round_significant(*value, digits);
+        }
+      }
+    }
+    entries
+  }
+
+  // 不取走数据，按 finish 的方式处理一份副本（只读查询用），按音素名排序
+  fn finalized_copy(&self) -> Vec<(String, Vec<MfccCalibrationData>)> {
+    let mut entries: Vec<_> = self
+      .entries
+      .iter()
+      .map(|(name, list)| (name.clone(), list.clone()))
+      .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    self.finalize_entries(entries)
+  }
+
+  fn finalize_frames(&self, name: &str, data_list: &mut Vec<MfccCalibrationData>) {
+    let mean = match self.cmn_sums.get(name) {
+      Some(sum) if self.cmn && self.cmn_before_trim => Some(sum.mean()),
+      _ => None,
+    };
+    trim_frames(data_list, self.mfcc_data_count, self.retention);
+    // CMN 只作用于系数本身：两种统计范围都在拼上差分之前减均值，差分不受影响
+    if self.cmn {
+      let mean = mean.unwrap_or_else(|| stats::mean(data_list));
+      stats::subtract_mean(data_list, &mean);
+    }
+    if self.compute_deltas {
+      stats::append_deltas(data_list);
+    }
+    if self.use_standardization {
+      stats::standardize(data_list);
+    }
+  }
+
+  // 所有入库的帧都经过这里：去重 -> CMN 累计 -> 追加 -> 裁剪。返回是否真的存下了
+  fn store_frame(&mut self, phoneme_name: &str, array: Vec<f32>) -> bool {
+    if let Some(threshold) = self.dedup_threshold {
+      let last = self.entries.get(phoneme_name).and_then(|list| list.last());
+      if let Some(last) = last {
+        if self.distance(&last.array, &array) < threshold {
+          return false;
+        }
+      }
+    }
+
+    if self.reject_exact_duplicates {
+      let hash = frame_hash(&array);
+      let hashes = self
+        .frame_hashes
+        .entry(phoneme_name.to_string())
+        .or_default();
+      let duplicate = hashes.contains_key(&hash)
+        && self.entries.get(phoneme_name).is_some_and(|list| {
+          list.iter().any(|data| {
+            data
+              .array
+              .iter()
+              .map(|v| v.to_bits())
+              .eq(array.iter().map(|v| v.to_bits()))
+          })
+        });
+      if duplicate {
+        return false;
+      }
+      *hashes.entry(hash).or_default() += 1;
+    }
+
+    if self.cmn && self.cmn_before_trim {
+      self
+        .cmn_sums
+        .entry(phoneme_name.to_string())
+        .or_default()
+        .push(&array);
+    }
+
+    self
+      .entries
+      .entry(phoneme_name.to_string())
+      .or_default()
+      .push(MfccCalibrationData { array });
+    if !self.defer_trim {
+      self.trim_overflow(phoneme_name);
+    }
+    true
+  }
+
+  fn add_batch(
     &mut self,
-    audio: Float32Array,
-    phoneme_name: String,
+    audios: &[Float32Array],
+    phoneme_name: &str,
     input_sample_rate: u32,
-  ) -> Result<()> {
-    if audio.is_empty() {
-      return Err(Error::new(Status::InvalidArg, "Audio data is empty"));
+    channels: Option<u32>,
+  ) -> Result<SampleReport> {
+    let mut total = SampleReport::default();
+    for audio in audios {
+      if audio.is_empty() {
+        total.buffers_skipped_empty += 1;
+        continue;
+      }
+      let channels = check_channels(audio.len(), channels)?;
+      let report = self.add_audio(
+        audio,
+        channels,
+        phoneme_name.to_string(),
+        input_sample_rate,
+        |_| Ok(()),
+        |_| Ok(()),
+      )?;
+      total.frames_processed += report.frames_processed;
+      total.frames_dropped_nonfinite += report.frames_dropped_nonfinite;
+      total.frames_dropped_short += report.frames_dropped_short;
+      total.frames_skipped_silence += report.frames_skipped_silence;
+      total.samples_sanitized += report.samples_sanitized;
+      total.expected_sample_rate = total.expected_sample_rate.or(report.expected_sample_rate);
     }
+    Ok(total)
+  }
 
-    let audio_data: Vec<f32> = audio.to_vec();
-    let total = audio_data.len();
-
-    thread_local! {
-      static MFCC_POOL: RefCell<mfcc::MfccBufferPool> = RefCell::new(mfcc::MfccBufferPool::new());
+  // 帧数超过上限时按 retentionPolicy 裁剪，并同步去掉被丢弃帧的哈希
+  fn trim_overflow(&mut self, phoneme_name: &str) {
+    let Some(entry_list) = self.entries.get_mut(phoneme_name) else {
+      return;
+    };
+    // Representative 每次聚类代价较高，攒到两倍上限再裁剪
+    let limit = match self.retention {
+      RetentionPolicy::Fifo => self.mfcc_data_count,
+      RetentionPolicy::Representative => self.mfcc_data_count.saturating_mul(2),
+      RetentionPolicy::Unlimited => usize::MAX,
+    };
+    if entry_list.len() > limit {
+      let dropped = trim_frames(entry_list, self.mfcc_data_count, self.retention);
+      if let Some(hashes) = self.frame_hashes.get_mut(phoneme_name) {
+        for data in dropped {
+          let hash = frame_hash(&data.array);
+          if let Some(count) = hashes.get_mut(&hash) {
+            *count -= 1;
+            if *count == 0 {
+              hashes.remove(&hash);
+            }
+          }
+        }
+      }
     }
+  }
 
-    let mut mfcc_output: Vec<f32> = Vec::new();
-    let mut frame_buf: Vec<f32> = vec![0.0; self.sample_count];
+  // 提取一帧特征，结果含非有限值时返回 false
+  fn extract(
+    &mut self,
+    frame: &mut [f32],
+    warmup: usize,
+    input_sample_rate: u32,
+    out: &mut Vec<f32>,
+  ) -> bool {
+    mfcc::extract_mfcc(
+      frame,
+      warmup,
+      input_sample_rate,
+      &self.config,
+      &mut self.pool,
+      out,
+    );
+    out.iter().all(|v| v.is_finite())
+  }
 
-    let entry_list = self.entries.entry(phoneme_name).or_default();
+  // 与 addSample 相同的分帧和提取，但不入库，只返回特征值有限的帧（只读查询用）。
+  // mel 为 true 时每帧返回 DCT 之前的对数 mel 谱
+  fn extract_frames(
+    &self,
+    audio: &[f32],
+    input_sample_rate: u32,
+    mel: bool,
+  ) -> Result<Vec<Vec<f32>>> {
+    if audio.is_empty() {
+      return Err(Error::new(Status::InvalidArg, "Audio data is empty"));
+    }
+    self.check_input_rate(input_sample_rate, self.sample_count)?;
 
-    let mut start = 0usize;
-    while start + self.sample_count <= total {
-      let end = start + self.sample_count;
-      frame_buf.copy_from_slice(&audio_data[start..end]);
-      MFCC_POOL.with(|pool_ref| {
-        let mut pool = pool_ref.borrow_mut();
-        mfcc::extract_mfcc(
-          &mut frame_buf,
-          input_sample_rate,
-          self.target_sample_rate,
-          self.mel_filter_bank_channels,
-          &mut pool,
-          &mut mfcc_output,
-        );
-      });
+    let mut source = AudioSource::new(audio, 1, self.stereo_mode);
+    if let Some(params) = &self.agc {
+      source.apply_agc(input_sample_rate, params);
+    }
+    let total = source.len();
+    let warmup = self.warmup(input_sample_rate);
+    let mut pool = mfcc::MfccBufferPool::new();
+    let mut frame_buf = vec![0.0; warmup + self.sample_count];
+    let mut frames = Vec::new();
 
-      if mfcc_output.iter().any(|&v| !v.is_finite()) {
-        start += self.sample_count;
+    let mut start = warmup.div_ceil(self.hop_size) * self.hop_size;
+    while start + self.sample_count <= total {
+      source.read(start, warmup, &mut frame_buf);
+      if audio::is_silent(self.silence_threshold(), &frame_buf, warmup) {
+        start += self.hop_size;
         continue;
       }
-      let result_data = std::mem::take(&mut mfcc_output);
-      let calibration_data = MfccCalibrationData { array: result_data };
-      entry_list.push(calibration_data);
-      if entry_list.len() > self.mfcc_data_count {
-        let overflow = entry_list.len() - self.mfcc_data_count;
-        entry_list.drain(0..overflow);
+      let mut out = Vec::new();
+      mfcc::extract_mfcc(
+        &mut frame_buf,
+        warmup,
+        input_sample_rate,
+        &self.config,
+        &mut pool,
+        &mut out,
+      );
+      if out.iter().all(|v| v.is_finite()) {
+        frames.push(if mel {
+          pool.mel_spectrum(&self.config)
+        } else {
+          out
+        });
       }
+      start += self.hop_size;
+    }
+    Ok(frames)
+  }
+
+  // 静音门限换算成线性幅度（满幅为 1）
+  fn silence_threshold(&self) -> Option<f32> {
+    self
+      .silence_threshold_db
+      .map(|db| 10f32.powf(db as f32 / 20.0))
+  }
 
-      start += self.sample_count;
+  fn warmup(&self, input_sample_rate: u32) -> usize {
+    if self.discard_filter_transient {
+      mfcc::filter_warmup(input_sample_rate, &self.config)
+    } else {
+      0
     }
+  }
 
-    Ok(())
+  // padShortInput：从 first 开始凑不出一帧时补零到刚好一帧
+  fn pad_short<'a>(&self, audio: &'a [f32], channels: usize, first: usize) -> Cow<'a, [f32]> {
+    let frames = audio.len() / channels;
+    if self.pad_short_input && frames < first + self.sample_count {
+      let mut padded = audio.to_vec();
+      padded.resize((first + self.sample_count) * channels, 0.0);
+      Cow::Owned(padded)
+    } else {
+      Cow::Borrowed(audio)
+    }
   }
 
-  #[napi]
-  pub fn finish(&mut self) -> Result<String> {
-    let mfcc_entries: Vec<MfccEntry> = self
-      .entries
-      .drain()
-      .map(|(name, data_list)| MfccEntry {
-        name,
-        mfcc_calibration_data_list: data_list,
-      })
-      .collect();
+  // addSample / addSampleEmit 共用入口。streaming 模式下先拼上该音素上次没用完的样本，
+  // 处理完再把下一帧需要的尾部（含预热样本）存回去
+  fn add_audio(
+    &mut self,
+    audio: &[f32],
+    channels: usize,
+    phoneme_name: String,
+    input_sample_rate: u32,
+    on_frame: impl FnMut(&[f32]) -> Result<()>,
+    on_progress: impl FnMut(u32) -> Result<()>,
+  ) -> Result<SampleReport> {
+    let phoneme_name = normalize_phoneme_name(phoneme_name)?;
+    if audio.is_empty() {
+      return Err(Error::new(Status::InvalidArg, "Audio data is empty"));
+    }
+    self.check_input_rate(input_sample_rate, self.sample_count)?;
+    let expected_sample_rate =
+      self.check_sample_rate_consistency(&phoneme_name, input_sample_rate)?;
+    self.record_sample_rate(&phoneme_name, input_sample_rate);
+    let warmup = self.warmup(input_sample_rate);
+    // 跳过前面凑不够预热样本的帧
+    let first = warmup.div_ceil(self.hop_size) * self.hop_size;
+    let (audio, sanitized) = self.sanitize(audio);
 
-    let output = OutputJson {
-      mfcc_num: MFCC_SIZE,
-      mfcc_data_count: self.mfcc_data_count,
-      mel_filter_bank_channels: self.mel_filter_bank_channels,
-      target_sample_rate: self.target_sample_rate,
-      sample_count: self.sample_count,
-      use_standardization: if self.use_standardization { 1 } else { 0 },
-      compare_method: self.compare_method.as_u32(),
-      mfccs: mfcc_entries,
+    if !self.streaming {
+      let audio = self.trim_silence(audio, channels, input_sample_rate);
+      let audio = self.pad_short(&audio, channels, first);
+      let source = AudioSource::new(&audio, channels, self.stereo_mode);
+      let (_, report) = self.process_audio(
+        source,
+        first,
+        &phoneme_name,
+        input_sample_rate,
+        on_frame,
+        on_progress,
+      )?;
+      return Ok(SampleReport {
+        samples_sanitized: sanitized,
+        expected_sample_rate,
+        ..report
+      });
+    }
+
+    if let Some(pending) = self.pending.get(&phoneme_name) {
+      if pending.channels != channels || pending.input_sample_rate != input_sample_rate {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "Pending audio for '{phoneme_name}' has {} channels at {} Hz, got {channels} channels at {input_sample_rate} Hz",
+            pending.channels, pending.input_sample_rate
+          ),
+        ));
+      }
+    }
+    let (mut data, start) = match self.pending.remove(&phoneme_name) {
+      Some(pending) => (pending.data, pending.start),
+      None => (Vec::new(), first),
     };
+    data.extend_from_slice(&audio);
 
-    serde_json::to_string(&output)
-      .map_err(|e| Error::new(Status::GenericFailure, format!("Serialization error: {e}")))
+    let source = AudioSource::new(&data, channels, self.stereo_mode);
+    let (next, report) = self.process_audio(
+      source,
+      start,
+      &phoneme_name,
+      input_sample_rate,
+      on_frame,
+      on_progress,
+    )?;
+    let keep_from = (next - warmup).min(data.len() / channels);
+    data.drain(..keep_from * channels);
+    self.pending.insert(
+      phoneme_name,
+      PendingAudio {
+        data,
+        start: next - keep_from,
+        channels,
+        input_sample_rate,
+      },
+    );
+    Ok(SampleReport {
+      frames_dropped_short: 0,
+      samples_sanitized: sanitized,
+      expected_sample_rate,
+      ..report
+    })
+  }
+
+  // 提取之前的检查：采样率有效，低通过渡带窄于截止频率，一帧重采样之后不为空
+  fn check_input_rate(&self, input_sample_rate: u32, window_len: usize) -> Result<()> {
+    check_sample_rate(input_sample_rate)?;
+    let target_sample_rate = self.config.target_sample_rate;
+    check_lpf_range(input_sample_rate, target_sample_rate, self.config.lpf_range)?;
+    check_resampled_len(window_len, input_sample_rate, target_sample_rate)
+  }
+
+  // 与该音素第一次的采样率不同时返回第一次的采样率，strictSampleRate 时报错
+  fn check_sample_rate_consistency(
+    &self,
+    phoneme_name: &str,
+    input_sample_rate: u32,
+  ) -> Result<Option<u32>> {
+    match self.sample_rates.get(phoneme_name) {
+      Some(&expected) if expected != input_sample_rate => {
+        if self.strict_sample_rate {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!(
+              "Phoneme '{phoneme_name}' was recorded at {expected} Hz, got {input_sample_rate} Hz"
+            ),
+          ));
+        }
+        Ok(Some(expected))
+      }
+      _ => Ok(None),
+    }
+  }
+
+  fn record_sample_rate(&mut self, phoneme_name: &str, input_sample_rate: u32) {
+    self
+      .sample_rates
+      .entry(phoneme_name.to_string())
+      .or_insert(input_sample_rate);
+  }
+
+  fn trim_silence<'a>(
+    &self,
+    audio: Cow<'a, [f32]>,
+    channels: usize,
+    input_sample_rate: u32,
+  ) -> Cow<'a, [f32]> {
+    let Some(db) = self.trim_silence_db else {
+      return audio;
+    };
+    let threshold = 10f32.powf(db as f32 / 20.0);
+    let margin = input_sample_rate as usize * TRIM_SILENCE_MARGIN_MS / 1000;
+    let range = algorithm::trim_silence(&audio, channels, threshold, margin);
+    match audio {
+      Cow::Borrowed(data) => Cow::Borrowed(&data[range]),
+      Cow::Owned(data) => Cow::Owned(data[range].to_vec()),
+    }
+  }
+
+  fn sanitize<'a>(&self, audio: &'a [f32]) -> (Cow<'a, [f32]>, u32) {
+    if !self.sanitize_input {
+      return (Cow::Borrowed(audio), 0);
+    }
+    let (audio, count) = audio::sanitize(audio);
+    (audio, count as u32)
+  }
+
+  // 各帧分给 rayon 线程并行提取（每个线程一份缓冲池），结果按帧顺序返回
+  #[cfg(feature = "rayon")]
+  fn extract_parallel(
+    &self,
+    source: &AudioSource,
+    starts: &[usize],
+    warmup: usize,
+    input_sample_rate: u32,
+  ) -> Vec<FrameOutcome> {
+    use rayon::prelude::*;
+
+    let frame_len = warmup + self.sample_count;
+    starts
+      .par_iter()
+      .map_init(
+        || (mfcc::MfccBufferPool::new(), vec![0.0; frame_len]),
+        |(pool, frame_buf), &start| {
+          audio::extract_outcome(
+            &self.config,
+            self.silence_threshold(),
+            source,
+            start,
+            warmup,
+            input_sample_rate,
+            pool,
+            frame_buf,
+          )
+        },
+      )
+      .collect()
+  }
+
+  // 从 start 开始每隔 hopSize 取一帧，返回第一个没有处理的帧起点和统计。
+  // 每处理 progressInterval 个窗口（含被跳过的）调用一次 on_progress
+  fn process_audio(
+    &mut self,
+    mut source: AudioSource,
+    mut start: usize,
+    phoneme_name: &str,
+    input_sample_rate: u32,
+    mut on_frame: impl FnMut(&[f32]) -> Result<()>,
+    mut on_progress: impl FnMut(u32) -> Result<()>,
+  ) -> Result<(usize, SampleReport)> {
+    if let Some(params) = &self.agc {
+      source.apply_agc(input_sample_rate, params);
+    }
+    let total = source.len();
+    let warmup = self.warmup(input_sample_rate);
+
+    let mut report = SampleReport::default();
+    let mut chunks: u32 = 0;
+
+    #[cfg(feature = "rayon")]
+    if self.parallel {
+      let starts: Vec<usize> = (start..)
+        .step_by(self.hop_size)
+        .take_while(|s| s + self.sample_count <= total)
+        .collect();
+      let frames = self.extract_parallel(&source, &starts, warmup, input_sample_rate);
+      for frame in frames {
+        match frame {
+          FrameOutcome::Features(result_data) => {
+            on_frame(&result_data)?;
+            self.store_frame(phoneme_name, result_data);
+            report.frames_processed += 1;
+          }
+          FrameOutcome::NonFinite => report.frames_dropped_nonfinite += 1,
+          FrameOutcome::Silent => report.frames_skipped_silence += 1,
+        }
+        chunks += 1;
+        if chunks.is_multiple_of(self.progress_interval) {
+          on_progress(chunks)?;
+        }
+      }
+      start += starts.len() * self.hop_size;
+    }
+
+    let mut mfcc_output: Vec<f32> = Vec::new();
+    let mut frame_buf: Vec<f32> = vec![0.0; warmup + self.sample_count];
+
+    while start + self.sample_count <= total {
+      source.read(start, warmup, &mut frame_buf);
+      if audio::is_silent(self.silence_threshold(), &frame_buf, warmup) {
+        report.frames_skipped_silence += 1;
+      } else if !self.extract(&mut frame_buf, warmup, input_sample_rate, &mut mfcc_output) {
+        report.frames_dropped_nonfinite += 1;
+      } else {
+        let result_data = std::mem::take(&mut mfcc_output);
+        on_frame(&result_data)?;
+        self.store_frame(phoneme_name, result_data);
+        report.frames_processed += 1;
+      }
+
+      start += self.hop_size;
+      chunks += 1;
+      if chunks.is_multiple_of(self.progress_interval) {
+        on_progress(chunks)?;
+      }
+    }
+    report.frames_dropped_short = total.saturating_sub(start).div_ceil(self.hop_size) as u32;
+
+    Ok((start, report))
+  }
+}
+
+#[napi]
+impl ProfileGenerator {
+  /// 所有选项都在构造时校验，不合法时抛出 `InvalidArg`（`fromJson` 同样经过这里）。
+  #[napi(constructor)]
+  pub fn new(opts: ProfileGeneratorOptions) -> Result<Self> {
+    if opts.target_sample_rate == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "targetSampleRate must be greater than 0",
+      ));
+    }
+    if opts.mel_filter_bank_channels == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "melFilterBankChannels must be greater than 0",
+      ));
+    }
+    if opts.sample_count == Some(0) {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "sampleCount must be greater than 0",
+      ));
+    }
+
+    if let Some(digits) = opts.float_precision {
+      if !(1..=9).contains(&digits) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("floatPrecision must be between 1 and 9, got {digits}"),
+        ));
+      }
+    }
+
+    let mfcc_data_count = opts.mfcc_data_count.unwrap_or(16);
+    if mfcc_data_count == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "mfccDataCount must be greater than 0 (use retentionPolicy Unlimited to keep every frame)",
+      ));
+    }
+    if mfcc_data_count > MAX_MFCC_DATA_COUNT && !opts.allow_large_capacity.unwrap_or(false) {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "mfccDataCount must be at most {MAX_MFCC_DATA_COUNT}, got {mfcc_data_count} (set allowLargeCapacity to raise the limit)"
+        ),
+      ));
+    }
+
+    if let Some(target) = opts.agc_target_rms {
+      if !(target.is_finite() && target > 0.0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("agcTargetRms must be a positive number, got {target}"),
+        ));
+      }
+    }
+
+    if let Some(num) = opts.mfcc_num {
+      let max = opts.mel_filter_bank_channels.saturating_sub(1);
+      if num == 0 || num > max {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("mfccNum must be between 1 and melFilterBankChannels - 1 ({max}), got {num}"),
+        ));
+      }
+    }
+
+    if let Some(weights) = &opts.coefficient_weights {
+      let expected = match opts.feature_type.unwrap_or(FeatureType::Mfcc) {
+        FeatureType::Mfcc => Some(
+          opts.mfcc_num.map_or(MFCC_SIZE, |num| num as usize)
+            + usize::from(opts.keep_c0.unwrap_or(false)),
+        ),
+        FeatureType::Spectrum => opts.spectrum_bins.map(|bins| bins as usize),
+      };
+      let Some(expected) = expected else {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "coefficientWeights with featureType Spectrum requires spectrumBins",
+        ));
+      };
+      if weights.len() != expected {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "coefficientWeights must have {expected} elements, got {}",
+            weights.len()
+          ),
+        ));
+      }
+      if let Some(w) = weights.iter().find(|w| !(w.is_finite() && **w >= 0.0)) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("coefficientWeights must be non-negative numbers, got {w}"),
+        ));
+      }
+    }
+
+    let sample_count = opts.sample_count.unwrap_or(1024);
+    let hop_size = opts.hop_size.unwrap_or(sample_count);
+    if hop_size == 0 || hop_size > sample_count {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("hopSize must be between 1 and sampleCount ({sample_count}), got {hop_size}"),
+      ));
+    }
+
+    if let Some(coeff) = opts.pre_emphasis_coeff {
+      if !(0.0..1.0).contains(&coeff) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("preEmphasisCoeff must be in [0, 1), got {coeff}"),
+        ));
+      }
+    }
+
+    if opts.parallel == Some(true) && !cfg!(feature = "rayon") {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "parallel requires the `rayon` feature",
+      ));
+    }
+
+    if let Some(eps) = opts.cosine_epsilon {
+      if !(eps.is_finite() && eps > 0.0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("cosineEpsilon must be a positive number, got {eps}"),
+        ));
+      }
+    }
+
+    if opts.mel_fmin.is_some() || opts.mel_fmax.is_some() {
+      let nyquist = opts.target_sample_rate as f64 / 2.0;
+      let fmin = opts.mel_fmin.unwrap_or(0.0);
+      let fmax = opts.mel_fmax.unwrap_or(nyquist);
+      if !(fmin.is_finite() && fmin >= 0.0 && fmin < fmax && fmax <= nyquist) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "melFmin / melFmax must satisfy 0 <= melFmin < melFmax <= targetSampleRate / 2 ({nyquist}), got {fmin} / {fmax}"
+          ),
+        ));
+      }
+    }
+
+    if opts.ulipsync_exact == Some(true) {
+      let conflicts = [
+        ("useSlaneyMel", opts.use_slaney_mel == Some(true)),
+        ("melFmin", opts.mel_fmin.is_some()),
+        ("melFmax", opts.mel_fmax.is_some()),
+        ("melNorm", matches!(opts.mel_norm, Some(MelNorm::None))),
+        ("highPrecision", opts.high_precision == Some(true)),
+      ];
+      if let Some((name, _)) = conflicts.iter().find(|(_, set)| *set) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("ulipsyncExact cannot be combined with {name}"),
+        ));
+      }
+    }
+
+    if let Some(cutoff) = opts.highpass_cutoff {
+      let max = opts.target_sample_rate as f64 / 2.0;
+      if !(cutoff.is_finite() && cutoff > 0.0 && cutoff < max) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "highpassCutoff must be between 0 and targetSampleRate / 2 ({max}), got {cutoff}"
+          ),
+        ));
+      }
+    }
+
+    if let Some(amount) = opts.dither_amount {
+      if !(amount.is_finite() && amount >= 0.0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("ditherAmount must be a non-negative number, got {amount}"),
+        ));
+      }
+    }
+
+    if let Some(peak) = opts.normalize_peak {
+      if !(peak.is_finite() && peak >= 0.0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("normalizePeak must be a non-negative number, got {peak}"),
+        ));
+      }
+    }
+
+    if let Some(range) = opts.lpf_transition_range {
+      let max = opts.target_sample_rate as f64 / 2.0;
+      if !(range.is_finite() && range > 0.0 && range < max) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "lpfTransitionRange must be between 0 and targetSampleRate / 2 ({max}), got {range}"
+          ),
+        ));
+      }
+    }
+
+    if let Some(threshold) = opts.dedup_threshold {
+      if !(threshold.is_finite() && threshold >= 0.0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("dedupThreshold must be a non-negative number, got {threshold}"),
+        ));
+      }
+    }
+
+    let progress_interval = opts.progress_interval.unwrap_or(256);
+    if progress_interval == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "progressInterval must be at least 1",
+      ));
+    }
+
+    if let Some(l) = opts.lifter {
+      if !(l.is_finite() && l >= 0.0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("lifter must be a non-negative number, got {l}"),
+        ));
+      }
+    }
+
+    if let Some(db) = opts.silence_threshold_db {
+      if !(db.is_finite() && db <= 0.0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("silenceThresholdDb must be a finite number <= 0, got {db}"),
+        ));
+      }
+    }
+
+    if let Some(db) = opts.trim_silence_db {
+      if !(db.is_finite() && db <= 0.0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("trimSilenceDb must be a finite number <= 0, got {db}"),
+        ));
+      }
+      if opts.streaming == Some(true) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "trimSilenceDb is not supported in streaming mode",
+        ));
+      }
+    }
+
+    if opts.spectrum_bins == Some(0) {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "spectrumBins must be at least 1",
+      ));
+    }
+
+    let stages = match opts.pipeline_order {
+      None => mfcc::DEFAULT_STAGES,
+      Some(order) => parse_pipeline_order(&order)?,
+    };
+
+    let pad_fft_to_pow2 = opts.pad_fft_to_pow2.unwrap_or(false);
+    let mut warnings = Vec::new();
+    if !sample_count.is_power_of_two() && !pad_fft_to_pow2 {
+      warnings.push(format!(
+        "sampleCount {sample_count} is not a power of two; FFT will be slower (set padFftToPow2 to zero-pad)"
+      ));
+    }
+
+    Ok(Self {
+      config: mfcc::MfccConfig {
+        feature_type: opts.feature_type.unwrap_or(FeatureType::Mfcc),
+        spectrum_bins: opts.spectrum_bins.map(|bins| bins as usize),
+        log_base: opts.log_base.unwrap_or(LogBase::Db10),
+        stages,
+        mfcc_num: opts.mfcc_num.map_or(MFCC_SIZE, |num| num as usize),
+        slaney_mel: opts.use_slaney_mel.unwrap_or(false),
+        mel_fmin: opts.mel_fmin.unwrap_or(0.0) as f32,
+        mel_fmax: opts.mel_fmax.map(|fmax| fmax as f32),
+        mel_norm: opts.mel_norm.unwrap_or(MelNorm::SlaneyArea),
+        ulipsync_exact: opts.ulipsync_exact.unwrap_or(false),
+        window: opts.window.unwrap_or(WindowFunction::Hamming),
+        normalize: opts.normalize_mode.unwrap_or(NormalizeMode::Peak),
+        normalize_peak: opts.normalize_peak.map_or(1.0, |peak| peak as f32),
+        remove_dc: opts.remove_dc.unwrap_or(false),
+        highpass_cutoff: opts.highpass_cutoff.map(|cutoff| cutoff as f32),
+        dither: opts.dither_amount.unwrap_or(0.0) as f32,
+        pre_emphasis: opts.pre_emphasis_coeff.map_or(0.97, |coeff| coeff as f32),
+        lifter: opts.lifter.unwrap_or(0.0) as f32,
+        dct_ortho: opts.dct_ortho.unwrap_or(false),
+        keep_c0: opts.keep_c0.unwrap_or(false),
+        lpf_range: opts
+          .lpf_transition_range
+          .map_or(mfcc::DEFAULT_LPF_RANGE, |range| range as f32),
+        resample_quality: opts.resample_quality.unwrap_or(ResampleQuality::Low),
+        high_precision: opts.high_precision.unwrap_or(false),
+        pad_fft_to_pow2,
+        ..mfcc::MfccConfig::new(
+          opts.target_sample_rate,
+          opts.mel_filter_bank_channels as usize,
+        )
+      },
+      pool: mfcc::MfccBufferPool::new(),
+      compare_method: opts.compare_method.unwrap_or(CompareMethod::L2Norm),
+      coefficient_weights: opts
+        .coefficient_weights
+        .as_ref()
+        .map(|weights| weights.iter().map(|&w| w as f32).collect()),
+      cosine_epsilon: opts
+        .cosine_epsilon
+        .map_or(distance::DEFAULT_COSINE_EPSILON, |eps| eps as f32),
+      entries: HashMap::new(),
+      mfcc_data_count: mfcc_data_count as usize,
+      sample_count: sample_count as usize,
+      hop_size: hop_size as usize,
+      warnings,
+      use_standardization: opts.use_standardization.unwrap_or(false),
+      defer_trim: opts.defer_trim.unwrap_or(false),
+      retention: opts.retention_policy.unwrap_or(RetentionPolicy::Fifo),
+      discard_filter_transient: opts.discard_filter_transient.unwrap_or(false),
+      stereo_mode: opts.stereo_mode.unwrap_or(StereoMode::Downmix),
+      float_precision: opts.float_precision,
+      cmn: opts.cmn.unwrap_or(false),
+      cmn_before_trim: opts.cmn_before_trim.unwrap_or(false),
+      cmvn: opts.cmvn.unwrap_or(false),
+      cmn_sums: HashMap::new(),
+      sample_rates: HashMap::new(),
+      strict_sample_rate: opts.strict_sample_rate.unwrap_or(false),
+      include_confidence: opts.include_confidence.unwrap_or(false),
+      include_trajectory: opts.include_trajectory.unwrap_or(false),
+      agc: opts.agc.unwrap_or(false).then(|| algorithm::Agc {
+        target_rms: opts.agc_target_rms.unwrap_or(0.1) as f32,
+        attack_ms: opts.agc_attack_ms.unwrap_or(5.0) as f32,
+        release_ms: opts.agc_release_ms.unwrap_or(200.0) as f32,
+      }),
+      reject_exact_duplicates: opts.reject_exact_duplicates.unwrap_or(false),
+      dedup_threshold: opts.dedup_threshold.map(|threshold| threshold as f32),
+      frame_hashes: HashMap::new(),
+      streaming: opts.streaming.unwrap_or(false),
+      pad_short_input: opts.pad_short_input.unwrap_or(false),
+      pretty: opts.pretty.unwrap_or(false),
+      progress_interval,
+      sanitize_input: opts.sanitize_input.unwrap_or(false),
+      compute_deltas: opts.compute_deltas.unwrap_or(false),
+      #[cfg(feature = "rayon")]
+      parallel: opts.parallel.unwrap_or(false),
+      pending: HashMap::new(),
+      silence_threshold_db: opts.silence_threshold_db,
+      trim_silence_db: opts.trim_silence_db,
+    })
+  }
+
+  /// 从之前 `finish` 输出的 profile JSON 恢复，继续追加样本（仍按其中的 `mfccDataCount` 裁剪）。
+  /// 恢复 `targetSampleRate`、`melFilterBankChannels`、`mfccNum`、`compareMethod`、
+  /// `mfccDataCount`、`sampleCount`、`useStandardization`、`coefficientWeights`、`computeDeltas`、
+  /// `keepC0`，其余选项取默认值。`computeDeltas` 的 profile 只读回每帧前半段的系数，差分在 `finish` 时重新计算。
+  /// `formatVersion` 比当前版本新的 profile 会被拒绝，没有这个字段的旧 profile 照常读取。
+  #[napi(factory)]
+  pub fn from_json(json: String) -> Result<Self> {
+    let profile = parse_profile(&json)?;
+    let compare_method = CompareMethod::from_u32(profile.compare_method).ok_or_else(|| {
+      Error::new(
+        Status::InvalidArg,
+        format!("Unknown compareMethod {}", profile.compare_method),
+      )
+    })?;
+
+    // 带差分的 profile 每帧是系数 + 同样长度的差分
+    let coeffs = if profile.compute_deltas {
+      if !profile.mfcc_num.is_multiple_of(2) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "computeDeltas profile must have an even mfccNum, got {}",
+            profile.mfcc_num
+          ),
+        ));
+      }
+      profile.mfcc_num / 2
+    } else {
+      profile.mfcc_num
+    };
+    // keepC0 的 profile 每帧多出最前面的 c0，不计入 mfccNum 选项
+    let mfcc_num = if profile.keep_c0 {
+      coeffs.checked_sub(1).ok_or_else(|| {
+        Error::new(
+          Status::InvalidArg,
+          "keepC0 profile must have at least one coefficient per frame",
+        )
+      })?
+    } else {
+      coeffs
+    };
+
+    let mut generator = Self::new(ProfileGeneratorOptions {
+      target_sample_rate: profile.target_sample_rate,
+      mel_filter_bank_channels: profile.mel_filter_bank_channels as u32,
+      mfcc_num: Some(mfcc_num as u32),
+      compute_deltas: Some(profile.compute_deltas),
+      keep_c0: Some(profile.keep_c0),
+      compare_method: Some(compare_method),
+      coefficient_weights: profile
+        .coefficient_weights
+        .map(|weights| weights.into_iter().map(f64::from).collect()),
+      mfcc_data_count: Some(profile.mfcc_data_count as u32),
+      sample_count: Some(profile.sample_count as u32),
+      use_standardization: Some(profile.use_standardization != 0),
+      ..Default::default()
+    })?;
+
+    for entry in profile.mfccs {
+      for (i, mut data) in entry.mfcc_calibration_data_list.into_iter().enumerate() {
+        if data.array.len() != profile.mfcc_num {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!(
+              "Phoneme '{}' frame {i} has {} coefficients, but mfccNum is {}",
+              entry.name,
+              data.array.len(),
+              profile.mfcc_num
+            ),
+          ));
+        }
+        data.array.truncate(coeffs);
+        generator.store_frame(&entry.name, data.array);
+      }
+    }
+    Ok(generator)
+  }
+
+  /// `channels` 为交错声道数，默认 1；多声道时按 `stereoMode` 处理。
+  /// `phonemeName` 会去掉首尾空白，为空时报错；名字区分大小写，`A` 和 `a` 是两个音素。
+  /// `onProgress` 每处理 `progressInterval` 个窗口（含被跳过的）同步调用一次，参数为已处理的窗口数；
+  /// 回调抛错时立即停止，已处理的帧会保留。
+  #[napi]
+  pub fn add_sample(
+    &mut self,
+    audio: Float32Array,
+    phoneme_name: String,
+    input_sample_rate: u32,
+    channels: Option<u32>,
+    on_progress: Option<Function<u32, ()>>,
+  ) -> Result<()> {
+    let channels = check_channels(audio.len(), channels)?;
+    self
+      .add_audio(
+        &audio,
+        channels,
+        phoneme_name,
+        input_sample_rate,
+        |_| Ok(()),
+        |chunks| match &on_progress {
+          Some(callback) => callback.call(chunks),
+          None => Ok(()),
+        },
+      )
+      .map(|_| ())
+  }
+
+  /// 与 `addSample` 相同，额外返回本次处理、跳过的帧数，便于提示输入削波或静音。
+  #[napi]
+  pub fn add_sample_detailed(
+    &mut self,
+    audio: Float32Array,
+    phoneme_name: String,
+    input_sample_rate: u32,
+    channels: Option<u32>,
+  ) -> Result<SampleReport> {
+    let channels = check_channels(audio.len(), channels)?;
+    self.add_audio(
+      &audio,
+      channels,
+      phoneme_name,
+      input_sample_rate,
+      |_| Ok(()),
+      |_| Ok(()),
+    )
+  }
+
+  /// 把同一音素的多段录音（例如多次录制的短片段）一次送入，逐段按 `addSample` 处理，
+  /// 返回各段计数之和。空的录音直接跳过（计入 `buffersSkippedEmpty`），不会让整批报错。
+  /// 整批处理完才按 `retentionPolicy` 裁剪一次。
+  #[napi]
+  pub fn add_samples(
+    &mut self,
+    audios: Vec<Float32Array>,
+    phoneme_name: String,
+    input_sample_rate: u32,
+    channels: Option<u32>,
+  ) -> Result<SampleReport> {
+    let phoneme_name = normalize_phoneme_name(phoneme_name)?;
+    check_sample_rate(input_sample_rate)?;
+    let defer_trim = std::mem::replace(&mut self.defer_trim, true);
+    let result = self.add_batch(&audios, &phoneme_name, input_sample_rate, channels);
+    self.defer_trim = defer_trim;
+    if !defer_trim {
+      self.trim_overflow(&phoneme_name);
+    }
+    result
+  }
+
+  /// 与 `addSampleDetailed` 相同，但特征提取在 libuv 线程池里进行，不阻塞事件循环，
+  /// 适合一次导入很长的录音。使用调用时的配置，帧在 Promise 完成时才写入；
+  /// 在此之前调用 `finish` 不会包含这些帧。不支持 `streaming` 模式。
+  /// `onProgress` 与 `addSample` 相同，从后台线程异步投递到事件循环，不会阻塞提取。
+  #[napi(ts_return_type = "Promise<SampleReport>")]
+  pub fn add_sample_async(
+    &mut self,
+    reference: Reference<ProfileGenerator>,
+    audio: Float32Array,
+    phoneme_name: String,
+    input_sample_rate: u32,
+    channels: Option<u32>,
+    on_progress: Option<ThreadsafeFunction<u32, (), u32, Status, false>>,
+  ) -> Result<AsyncTask<AddSampleTask>> {
+    if self.streaming {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "addSampleAsync does not support streaming mode",
+      ));
+    }
+    let phoneme_name = normalize_phoneme_name(phoneme_name)?;
+    let channels = check_channels(audio.len(), channels)?;
+    if audio.is_empty() {
+      return Err(Error::new(Status::InvalidArg, "Audio data is empty"));
+    }
+    self.check_input_rate(input_sample_rate, self.sample_count)?;
+    let expected_sample_rate =
+      self.check_sample_rate_consistency(&phoneme_name, input_sample_rate)?;
+    // 创建任务时就记下采样率，同时在途的几个任务之间也能发现采样率不一致
+    self.record_sample_rate(&phoneme_name, input_sample_rate);
+    let warmup = self.warmup(input_sample_rate);
+    let first = warmup.div_ceil(self.hop_size) * self.hop_size;
+    let (audio, sanitized) = self.sanitize(&audio);
+    let audio = self.trim_silence(audio, channels, input_sample_rate);
+
+    Ok(AsyncTask::new(AddSampleTask {
+      audio: self.pad_short(&audio, channels, first).into_owned(),
+      sanitized,
+      expected_sample_rate,
+      generator: GeneratorRef(reference),
+      channels,
+      phoneme_name,
+      input_sample_rate,
+      config: self.config.clone(),
+      stereo_mode: self.stereo_mode,
+      agc: self.agc.clone(),
+      silence_threshold: self.silence_threshold(),
+      start: first,
+      warmup,
+      sample_count: self.sample_count,
+      hop_size: self.hop_size,
+      progress: on_progress,
+      progress_interval: self.progress_interval,
+    }))
+  }
+
+  /// 输入为 16 位整数 PCM（单声道），每个样本除以 32768 转成浮点后与 `addSample` 相同。
+  /// `-32768` 正好对应 -1.0，`32767` 对应略小于 1.0 的 `32767 / 32768`。
+  #[napi]
+  pub fn add_sample_pcm16(
+    &mut self,
+    audio: Int16Array,
+    phoneme_name: String,
+    input_sample_rate: u32,
+  ) -> Result<()> {
+    let samples = audio::pcm16_to_f32(&audio);
+    self
+      .add_audio(
+        &samples,
+        1,
+        phoneme_name,
+        input_sample_rate,
+        |_| Ok(()),
+        |_| Ok(()),
+      )
+      .map(|_| ())
+  }
+
+  /// 与 `addSample` 相同，但每提取出一帧就把它作为一行 JSON（`{"phoneme":...,"array":[...]}`）
+  /// 传给 `callback`；帧仍会照常累积。回调抛错时立即停止，已处理的帧会保留。
+  #[napi]
+  pub fn add_sample_emit(
+    &mut self,
+    audio: Float32Array,
+    phoneme_name: String,
+    input_sample_rate: u32,
+    callback: Function<String, ()>,
+  ) -> Result<()> {
+    let phoneme_name = normalize_phoneme_name(phoneme_name)?;
+    let phoneme = phoneme_name.clone();
+    self
+      .add_audio(
+        &audio,
+        1,
+        phoneme_name,
+        input_sample_rate,
+        |array| {
+          let line = serde_json::to_string(&FrameRecord {
+            phoneme: &phoneme,
+            array,
+          })
+          .map_err(|e| Error::new(Status::GenericFailure, format!("Serialization error: {e}")))?;
+          callback.call(line)
+        },
+        |_| Ok(()),
+      )
+      .map(|_| ())
+  }
+
+  /// streaming 模式下处理各音素剩下的样本。`pad` 为 true 时把剩余部分补零到一帧再提取，
+  /// 否则直接丢弃（不足一帧的样本无法单独成帧）。
+  #[napi]
+  pub fn flush_pending(&mut self, pad: Option<bool>) -> Result<()> {
+    let pending = std::mem::take(&mut self.pending);
+    if !pad.unwrap_or(false) {
+      return Ok(());
+    }
+    for (name, mut pending) in pending {
+      let frames = pending.data.len() / pending.channels;
+      if frames <= pending.start {
+        continue;
+      }
+      pending
+        .data
+        .resize((pending.start + self.sample_count) * pending.channels, 0.0);
+      let source = AudioSource::new(&pending.data, pending.channels, self.stereo_mode);
+      self.process_audio(
+        source,
+        pending.start,
+        &name,
+        pending.input_sample_rate,
+        |_| Ok(()),
+        |_| Ok(()),
+      )?;
+    }
+    Ok(())
+  }
+
+  /// 每个 `Float32Array` 作为一帧直接送入特征提取（不再切分），所有帧长度必须一致。
+  /// 返回得到有限特征值的帧数。
+  #[napi]
+  pub fn add_frames(
+    &mut self,
+    frames: Vec<Float32Array>,
+    phoneme_name: String,
+    input_sample_rate: u32,
+  ) -> Result<u32> {
+    let phoneme_name = normalize_phoneme_name(phoneme_name)?;
+    let frame_len = frames.first().map_or(0, |frame| frame.len());
+    if frame_len == 0 {
+      return Err(Error::new(Status::InvalidArg, "Frame data is empty"));
+    }
+    self.check_input_rate(input_sample_rate, frame_len)?;
+    if let Some((i, frame)) = frames
+      .iter()
+      .enumerate()
+      .find(|(_, frame)| frame.len() != frame_len)
+    {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("Frame {i} has length {}, expected {frame_len}", frame.len()),
+      ));
+    }
+
+    let mut frame_buf = vec![0.0; frame_len];
+    let mut output = Vec::new();
+    let mut produced = 0;
+    for frame in &frames {
+      frame_buf.copy_from_slice(frame);
+      if self.extract(&mut frame_buf, 0, input_sample_rate, &mut output) {
+        self.store_frame(&phoneme_name, std::mem::take(&mut output));
+        produced += 1;
+      }
+    }
+    Ok(produced)
+  }
+
+  /// 把另一个生成器的帧按音素追加进来（按 `mfccDataCount` 丢弃最旧的帧），只在一边出现的
+  /// 音素原样带过来。两者的 `targetSampleRate`、`melFilterBankChannels`、系数个数必须一致。
+  /// 各音素的输入采样率一并记录（`strictSampleRate` 时不一致直接报错）；不能与自身合并。
+  #[napi]
+  pub fn merge(&mut self, other: &ProfileGenerator) -> Result<()> {
+    // JS 里的 g.merge(g)：napi 的胶水代码在借出 &other 时发现对象已被可变借用，会直接抛错，
+    // 走不到这里。真到了这里 &mut self 和 &other 已经同时存在、互为别名，这个检查只是事后兜底，
+    // 防不住别名本身
+    if std::ptr::eq(self, other) {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "Cannot merge a generator into itself",
+      ));
+    }
+    let mismatch = |what: &str, ours: usize, theirs: usize| {
+      Err(Error::new(
+        Status::InvalidArg,
+        format!("Cannot merge generators with different {what}: {ours} vs {theirs}"),
+      ))
+    };
+    if self.config.target_sample_rate != other.config.target_sample_rate {
+      return mismatch(
+        "targetSampleRate",
+        self.config.target_sample_rate as usize,
+        other.config.target_sample_rate as usize,
+      );
+    }
+    if self.config.mel_filter_bank_channels != other.config.mel_filter_bank_channels {
+      return mismatch(
+        "melFilterBankChannels",
+        self.config.mel_filter_bank_channels,
+        other.config.mel_filter_bank_channels,
+      );
+    }
+    let (ours, theirs) = (self.feature_len(), other.feature_len());
+    if ours > 0 && theirs > 0 && ours != theirs {
+      return mismatch("mfccNum", ours, theirs);
+    }
+
+    // 先检查完再修改，strictSampleRate 报错时不留下合并了一半的数据
+    for (name, &rate) in &other.sample_rates {
+      self.check_sample_rate_consistency(name, rate)?;
+    }
+
+    let track_cmn = self.cmn && self.cmn_before_trim;
+    for (name, list) in &other.entries {
+      let previous = self.cmn_sums.remove(name);
+      for data in list {
+        self.store_frame(name, data.array.clone());
+      }
+      if track_cmn {
+        // 对方也在累计时用它的累计值（含已经被裁剪掉的帧），否则用刚才按保留帧累计的值
+        let added = match other.cmn_sums.get(name) {
+          Some(theirs) => Some(theirs.clone()),
+          None => self.cmn_sums.remove(name),
+        };
+        let mut sum = previous.unwrap_or_default();
+        if let Some(added) = added {
+          sum.merge(&added);
+        }
+        self.cmn_sums.insert(name.clone(), sum);
+      }
+      if let Some(&rate) = other.sample_rates.get(name) {
+        self.record_sample_rate(name, rate);
+      }
+    }
+    Ok(())
+  }
+
+  /// 把 `sources` 中各音素的帧按顺序追加到 `target`（按 `mfccDataCount` 裁剪），
+  /// 用于从几个安静的音素构造“闭口”之类的派生类别。`removeSources` 为 true 时删除源音素。
+  /// 任一源音素不存在时报错，且不做任何修改。
+  #[napi]
+  pub fn combine_phonemes(
+    &mut self,
+    sources: Vec<String>,
+    target: String,
+    remove_sources: Option<bool>,
+  ) -> Result<()> {
+    let target = normalize_phoneme_name(target)?;
+    let sources = sources
+      .into_iter()
+      .map(normalize_phoneme_name)
+      .collect::<Result<Vec<_>>>()?;
+    if let Some(missing) = sources
+      .iter()
+      .find(|name| !self.entries.contains_key(*name))
+    {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("Phoneme not found: {missing}"),
+      ));
+    }
+
+    let frames: Vec<Vec<f32>> = sources
+      .iter()
+      .flat_map(|name| self.entries[name].iter().map(|data| data.array.clone()))
+      .collect();
+    if remove_sources.unwrap_or(false) {
+      for name in sources.iter().filter(|name| **name != target) {
+        self.entries.remove(name);
+        self.cmn_sums.remove(name);
+        self.frame_hashes.remove(name);
+        self.sample_rates.remove(name);
+      }
+    }
+    for array in frames {
+      self.store_frame(&target, array);
+    }
+    Ok(())
+  }
+
+  /// 直接写入一帧已经算好的特征，返回是否被存下（开启 `rejectExactDuplicates` 时重复帧返回 false）。
+  #[napi]
+  pub fn add_mfcc_frame(&mut self, frame: Float32Array, phoneme_name: String) -> Result<bool> {
+    let phoneme_name = normalize_phoneme_name(phoneme_name)?;
+    let expected = self.feature_len();
+    if expected > 0 && frame.len() != expected {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("Frame length {} does not match {expected}", frame.len()),
+      ));
+    }
+    if frame.iter().any(|v| !v.is_finite()) {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "Frame contains non-finite values",
+      ));
+    }
+    Ok(self.store_frame(&phoneme_name, frame.to_vec()))
+  }
+
+  #[napi]
+  pub fn finish(&mut self) -> Result<String> {
+    let output = self.take_output();
+    if self.pretty {
+      serde_json::to_string_pretty(&output)
+    } else {
+      serde_json::to_string(&output)
+    }
+    .map_err(|e| Error::new(Status::GenericFailure, format!("Serialization error: {e}")))
+  }
+
+  /// 与 `finish` 相同，但以 UTF-8 字节的 `Buffer` 返回 JSON，可以直接写文件或流，省去 JS 字符串的转换。
+  #[napi]
+  pub fn finish_bytes(&mut self) -> Result<Buffer> {
+    let output = self.take_output();
+    if self.pretty {
+      serde_json::to_vec_pretty(&output)
+    } else {
+      serde_json::to_vec(&output)
+    }
+    .map(Buffer::from)
+    .map_err(|e| Error::new(Status::GenericFailure, format!("Serialization error: {e}")))
+  }
+
+  /// 与 `finish` 相同，但直接把 JSON 写入 `path`，不经过 JS 字符串。打开文件失败时不清空数据。
+  #[napi]
+  pub fn finish_to_file(&mut self, path: String) -> Result<()> {
+    let io_error = |e: std::io::Error| Error::new(Status::GenericFailure, format!("IO error: {e}"));
+    // 先打开文件，路径无效时不丢失已累积的数据
+    let mut writer = BufWriter::new(File::create(&path).map_err(io_error)?);
+    let output = self.take_output();
+    if self.pretty {
+      serde_json::to_writer_pretty(&mut writer, &output)
+    } else {
+      serde_json::to_writer(&mut writer, &output)
+    }
+    .map_err(|e| Error::new(Status::GenericFailure, format!("Serialization error: {e}")))?;
+    writer.flush().map_err(io_error)
+  }
+
+  /// 修改比较方式，之后的 `finish`、`compare` 等都按新的方式计算，已累积的帧不受影响。
+  #[napi]
+  pub fn set_compare_method(&mut self, method: CompareMethod) {
+    self.compare_method = method;
+  }
+
+  /// 丢弃所有已累积的帧，保留配置，可以直接开始新一轮录制。
+  #[napi]
+  pub fn reset(&mut self) {
+    self.entries.clear();
+    self.pending.clear();
+    self.cmn_sums.clear();
+    self.frame_hashes.clear();
+    self.sample_rates.clear();
+  }
+
+  /// 只删除一个音素的帧，用于重录单个音。返回该音素之前是否存在。
+  /// 与 `addSample` 一样，按名字查找音素时都会先去掉首尾空白。
+  #[napi]
+  pub fn remove_phoneme(&mut self, name: String) -> bool {
+    let name = name.trim();
+    self.pending.remove(name);
+    self.cmn_sums.remove(name);
+    self.frame_hashes.remove(name);
+    self.sample_rates.remove(name);
+    self.entries.remove(name).is_some()
+  }
+
+  /// 构造时发现的、不影响使用但可能不是本意的配置问题（例如 `sampleCount` 不是 2 的幂），
+  /// 没有时为空数组。
+  #[napi]
+  pub fn get_warnings(&self) -> Vec<String> {
+    self.warnings.clone()
+  }
+
+  /// 当前生效的配置，未设置的选项填入默认值（`compareMethod` 反映 `setCompareMethod` 之后的值）。
+  /// 没有默认值的可选项（`floatPrecision`、`spectrumBins`、`silenceThresholdDb` 等）未设置时为空。
+  /// AGC 关闭时 `agcTargetRms` 等给出的是默认参数。
+  #[napi]
+  pub fn get_options(&self) -> ProfileGeneratorOptions {
+    let agc = self.agc.clone().unwrap_or(algorithm::Agc {
+      target_rms: 0.1,
+      attack_ms: 5.0,
+      release_ms: 200.0,
+    });
+    ProfileGeneratorOptions {
+      target_sample_rate: self.config.target_sample_rate,
+      mel_filter_bank_channels: self.config.mel_filter_bank_channels as u32,
+      mfcc_num: Some(self.config.mfcc_num as u32),
+      compare_method: Some(self.compare_method),
+      coefficient_weights: self
+        .coefficient_weights
+        .as_ref()
+        .map(|weights| weights.iter().copied().map(widen).collect()),
+      cosine_epsilon: Some(widen(self.cosine_epsilon)),
+      mfcc_data_count: Some(self.mfcc_data_count as u32),
+      sample_count: Some(self.sample_count as u32),
+      hop_size: Some(self.hop_size as u32),
+      use_standardization: Some(self.use_standardization),
+      defer_trim: Some(self.defer_trim),
+      retention_policy: Some(self.retention),
+      discard_filter_transient: Some(self.discard_filter_transient),
+      stereo_mode: Some(self.stereo_mode),
+      float_precision: self.float_precision,
+      cmn: Some(self.cmn),
+      cmn_before_trim: Some(self.cmn_before_trim),
+      cmvn: Some(self.cmvn),
+      feature_type: Some(self.config.feature_type),
+      spectrum_bins: self.config.spectrum_bins.map(|bins| bins as u32),
+      include_confidence: Some(self.include_confidence),
+      include_trajectory: Some(self.include_trajectory),
+      agc: Some(self.agc.is_some()),
+      agc_target_rms: Some(widen(agc.target_rms)),
+      agc_attack_ms: Some(widen(agc.attack_ms)),
+      agc_release_ms: Some(widen(agc.release_ms)),
+      log_base: Some(self.config.log_base),
+      reject_exact_duplicates: Some(self.reject_exact_duplicates),
+      dedup_threshold: self.dedup_threshold.map(widen),
+      pipeline_order: Some(self.config.stages.to_vec()),
+      use_slaney_mel: Some(self.config.slaney_mel),
+      mel_fmin: Some(widen(self.config.mel_fmin)),
+      mel_fmax: Some(
+        self
+          .config
+          .mel_fmax
+          .map_or(self.config.target_sample_rate as f64 / 2.0, widen),
+      ),
+      mel_norm: Some(self.config.mel_norm),
+      ulipsync_exact: Some(self.config.ulipsync_exact),
+      normalize_mode: Some(self.config.normalize),
+      normalize_peak: Some(widen(self.config.normalize_peak)),
+      remove_dc: Some(self.config.remove_dc),
+      highpass_cutoff: self.config.highpass_cutoff.map(widen),
+      dither_amount: Some(widen(self.config.dither)),
+      window: Some(self.config.window),
+      pre_emphasis_coeff: Some(widen(self.config.pre_emphasis)),
+      lifter: Some(widen(self.config.lifter)),
+      dct_ortho: Some(self.config.dct_ortho),
+      keep_c0: Some(self.config.keep_c0),
+      lpf_transition_range: Some(widen(self.config.lpf_range)),
+      resample_quality: Some(self.config.resample_quality),
+      high_precision: Some(self.config.high_precision),
+      pad_fft_to_pow2: Some(self.config.pad_fft_to_pow2),
+      allow_large_capacity: Some(self.mfcc_data_count > MAX_MFCC_DATA_COUNT as usize),
+      streaming: Some(self.streaming),
+      pad_short_input: Some(self.pad_short_input),
+      #[cfg(feature = "rayon")]
+      parallel: Some(self.parallel),
+      #[cfg(not(feature = "rayon"))]
+      parallel: Some(false),
+      pretty: Some(self.pretty),
+      progress_interval: Some(self.progress_interval),
+      sanitize_input: Some(self.sanitize_input),
+      strict_sample_rate: Some(self.strict_sample_rate),
+      compute_deltas: Some(self.compute_deltas),
+      silence_threshold_db: self.silence_threshold_db,
+      trim_silence_db: self.trim_silence_db,
+    }
+  }
+
+  /// 已有帧的音素名，按字典序排列。
+  #[napi]
+  pub fn get_phoneme_names(&self) -> Vec<String> {
+    let mut names: Vec<String> = self.entries.keys().cloned().collect();
+    names.sort();
+    names
+  }
+
+  /// 该音素在 `finish` 时会输出的帧数（不超过 `mfccDataCount`，`Unlimited` 时为全部帧数），
+  /// 未知音素返回 0。
+  #[napi]
+  pub fn get_sample_count(&self, phoneme_name: String) -> u32 {
+    let cap = match self.retention {
+      RetentionPolicy::Unlimited => usize::MAX,
+      _ => self.mfcc_data_count,
+    };
+    self
+      .entries
+      .get(phoneme_name.trim())
+      .map_or(0, |list| list.len().min(cap) as u32)
+  }
+
+  /// 该音素当前累积的原始帧（未经 `finish` 时的裁剪、归一化等处理），未知音素返回空数组。
+  #[napi]
+  pub fn get_mfccs(&self, phoneme_name: String) -> Vec<Float32Array> {
+    self
+      .entries
+      .get(phoneme_name.trim())
+      .map_or_else(Vec::new, |list| {
+        list
+          .iter()
+          .map(|data| Float32Array::new(data.array.clone()))
+          .collect()
+      })
+  }
+
+  /// 按 `addSample` 的方式分帧（单声道），返回每帧 DCT 之前的 mel 滤波器组输出，
+  /// 长度为 `melFilterBankChannels`，按 `logBase` 取对数（默认 dB）。不入库，
+  /// 用于画出生成器"听到"的 mel 频谱图，检查录音电平是否合适。需要 `featureType` 为 `Mfcc`。
+  #[napi]
+  pub fn mel_spectrogram(
+    &self,
+    audio: Float32Array,
+    input_sample_rate: u32,
+  ) -> Result<Vec<Float32Array>> {
+    if let FeatureType::Spectrum = self.config.feature_type {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "melSpectrogram requires featureType Mfcc",
+      ));
+    }
+    Ok(
+      self
+        .extract_frames(&audio, input_sample_rate, true)?
+        .into_iter()
+        .map(Float32Array::new)
+        .collect(),
+    )
+  }
+
+  /// 每个音素的帧数以及逐系数的均值、方差，基于 `finish` 将会输出的帧，不修改已累积的数据。
+  #[napi]
+  pub fn stats(&self) -> HashMap<String, PhonemeStats> {
+    self
+      .finalized_copy()
+      .into_iter()
+      .map(|(name, frames)| {
+        let mean = stats::mean(&frames);
+        let variance = stats::variance(&frames, &mean);
+        let stats = PhonemeStats {
+          count: frames.len() as u32,
+          mean,
+          variance,
+        };
+        (name, stats)
+      })
+      .collect()
+  }
+
+  /// 用当前累积的数据给一段录音打分：按 `addSample` 的方式分帧提取，取所有帧的平均向量，
+  /// 与每个音素保留帧（按 `retentionPolicy` 裁剪到 `mfccDataCount`，不做 CMN、标准化等处理）的平均向量按
+  /// `compareMethod`（按 `coefficientWeights` 加权）比较。结果按从最像到最不像排序，
+  /// 分数相同时按音素名排序。
+  #[napi]
+  pub fn compare(&self, audio: Float32Array, input_sample_rate: u32) -> Result<Vec<PhonemeScore>> {
+    let frames: Vec<MfccCalibrationData> = self
+      .extract_frames(&audio, input_sample_rate, false)?
+      .into_iter()
+      .map(|array| MfccCalibrationData { array })
+      .collect();
+    if frames.is_empty() {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "No valid frames could be extracted from audio",
+      ));
+    }
+    let to_f32 = |mean: Vec<f64>| -> Vec<f32> { mean.iter().map(|&m| m as f32).collect() };
+    let input = to_f32(stats::mean(&frames));
+
+    let higher_is_better = self.compare_method.higher_is_better();
+    let mut scores: Vec<PhonemeScore> = self
+      .entries
+      .iter()
+      .map(|(name, list)| {
+        let mut retained = list.clone();
+        trim_frames(&mut retained, self.mfcc_data_count, self.retention);
+        let centroid = to_f32(stats::mean(&retained));
+        let distance = self.distance(&input, &centroid) as f64;
+        PhonemeScore {
+          name: name.clone(),
+          score: if higher_is_better {
+            1.0 - distance
+          } else {
+            distance
+          },
+          higher_is_better,
+        }
+      })
+      .collect();
+    scores.sort_by(|a, b| {
+      let order = a.score.total_cmp(&b.score);
+      if higher_is_better {
+        order.reverse()
+      } else {
+        order
+      }
+      .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(scores)
+  }
+
+  /// 与 `compare` 相同，只返回最像的音素名；分数相同时取字典序最小的名字。
+  /// 还没有任何音素时报错。
+  #[napi]
+  pub fn classify(&self, audio: Float32Array, input_sample_rate: u32) -> Result<String> {
+    if self.entries.is_empty() {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "No phonemes have been recorded yet",
+      ));
+    }
+    let scores = self.compare(audio, input_sample_rate)?;
+    Ok(
+      scores
+        .into_iter()
+        .next()
+        .map(|score| score.name)
+        .unwrap_or_default(),
+    )
+  }
+
+  /// 每个音素 `帧数 × mfccNum` 矩阵的有效秩（奇异值归一化熵的指数）。
+  /// 接近 1 说明帧之间高度冗余，接近 `min(帧数, mfccNum)` 说明覆盖的变化更丰富。
+  #[napi]
+  pub fn effective_rank(&self) -> HashMap<String, f64> {
+    self
+      .finalized_copy()
+      .into_iter()
+      .map(|(name, frames)| {
+        let rows: Vec<&[f32]> = frames.iter().map(|data| data.array.as_slice()).collect();
+        let rank = linalg::effective_rank(&linalg::singular_values(&rows));
+        (name, rank)
+      })
+      .collect()
+  }
+
+  /// 只保留 `keep` 中列出的音素，重新序列化一个已生成的 profile；`keep` 里有不存在的音素时报错。
+  /// 输出按当前的结构序列化，所以 `formatVersion` 必须与当前版本相同：更新的 profile 会丢字段，
+  /// 更旧的会被误标成当前版本，两种情况都报错。旧 profile 可以先用 `fromJson` + `finish` 升级。
+  #[napi]
+  pub fn filter_profile(json: String, keep: Vec<String>) -> Result<String> {
+    let mut profile = parse_profile(&json)?;
+    if profile.format_version != FORMAT_VERSION {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "Profile formatVersion {} is older than {FORMAT_VERSION}; upgrade it with fromJson and finish first",
+          profile.format_version
+        ),
+      ));
+    }
+
+    if let Some(missing) = keep
+      .iter()
+      .find(|name| !profile.mfccs.iter().any(|entry| &entry.name == *name))
+    {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("Phoneme '{missing}' not found in profile"),
+      ));
+    }
+    profile.mfccs.retain(|entry| keep.contains(&entry.name));
+
+    serde_json::to_string(&profile)
+      .map_err(|e| Error::new(Status::GenericFailure, format!("Serialization error: {e}")))
+  }
+
+  /// 与参考 profile 逐音素、逐帧、逐系数比较，绝对误差超过 `tolerance` 时返回第一处差异。
+  /// 比较的是 `finish` 将会输出的帧（裁剪、归一化等处理之后）。
+  #[napi]
+  pub fn assert_matches(&self, reference_json: String, tolerance: f64) -> Result<()> {
+    let reference: OutputJson = serde_json::from_str(&reference_json).map_err(|e| {
+      Error::new(
+        Status::InvalidArg,
+        format!("Invalid reference profile: {e}"),
+      )
+    })?;
+    let mismatch = |msg: String| Err(Error::new(Status::GenericFailure, msg));
+
+    let finalized: HashMap<String, Vec<MfccCalibrationData>> =
+      self.finalized_copy().into_iter().collect();
+    for entry in &reference.mfccs {
+      let Some(frames) = finalized.get(&entry.name) else {
+        return mismatch(format!("Phoneme '{}' missing from profile", entry.name));
+      };
+      let expected = &entry.mfcc_calibration_data_list;
+      if frames.len() != expected.len() {
+        return mismatch(format!(
+          "Phoneme '{}': frame count {} vs reference {}",
+          entry.name,
+          frames.len(),
+          expected.len()
+        ));
+      }
+      for (i, (actual, expected)) in frames.iter().zip(expected).enumerate() {
+        if actual.array.len() != expected.array.len() {
+          return mismatch(format!(
+            "Phoneme '{}' frame {i}: length {} vs reference {}",
+            entry.name,
+            actual.array.len(),
+            expected.array.len()
+          ));
+        }
+        for (j, (&a, &b)) in actual.array.iter().zip(&expected.array).enumerate() {
+          let diff = (a as f64 - b as f64).abs();
+          if diff.is_nan() || diff > tolerance {
+            return mismatch(format!(
+              "Phoneme '{}' frame {i} coefficient {j}: {a} vs reference {b} (diff {diff} > {tolerance})",
+              entry.name
+            ));
+          }
+        }
+      }
+    }
+
+    let mut extra: Vec<&String> = self
+      .entries
+      .keys()
+      .filter(|name| !reference.mfccs.iter().any(|entry| &entry.name == *name))
+      .collect();
+    extra.sort();
+    if let Some(name) = extra.first() {
+      return mismatch(format!("Phoneme '{name}' not present in reference"));
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(feature = "msgpack")]
+#[napi]
+impl ProfileGenerator {
+  /// 与 `finish` 相同，但输出 MessagePack（需要 `msgpack` feature）。结构和字段名与 JSON 完全一致
+  /// （map 形式，键为 `mfccNum`、`melFilterBankChannels` 等），帧多时体积明显更小。
+  #[napi]
+  pub fn finish_msgpack(&mut self) -> Result<Buffer> {
+    let output = self.take_output();
+    rmp_serde::to_vec_named(&output)
+      .map(Buffer::from)
+      .map_err(|e| Error::new(Status::GenericFailure, format!("Serialization error: {e}")))
+  }
+}
+
+#[cfg(feature = "png")]
+#[napi]
+impl ProfileGenerator {
+  /// 把某个音素的帧导出为灰度 PNG（需要 `png` feature）。
+  /// 横轴为帧，纵轴为 mel 通道（低频在下）；由 MFCC 逆 DCT 近似还原 log-mel，
+  /// `Spectrum` 特征则直接画幅度谱的 dB。整图 min/max 归一化到 0..255，越亮能量越高。
+  #[napi]
+  pub fn export_spectrogram_png(&self, phoneme_name: String, path: String) -> Result<()> {
+    let frames = self
+      .entries
+      .get(phoneme_name.trim())
+      .filter(|frames| !frames.is_empty())
+      .ok_or_else(|| {
+        Error::new(
+          Status::InvalidArg,
+          format!("No frames recorded for phoneme: {phoneme_name}"),
+        )
+      })?;
+
+    spectrogram::write_png(
+      frames,
+      self.config.feature_type,
+      self.config.keep_c0,
+      self.config.mel_filter_bank_channels,
+      &path,
+    )
+    .map_err(|e| Error::new(Status::GenericFailure, format!("PNG export error: {e}")))
+  }
+}
+
+#[cfg(feature = "wav")]
+#[napi]
+impl ProfileGenerator {
+  /// 读取 WAV 文件并按 `addSample` 处理（需要 `wav` feature）。采样率和声道数取自文件头，
+  /// 多声道按 `stereoMode` 处理（默认 downmix）。支持 16 位整数 PCM 和 32 位浮点，其他格式报错。
+  #[napi]
+  pub fn add_sample_from_wav(&mut self, path: String, phoneme_name: String) -> Result<()> {
+    let wav = wav::read(&path).map_err(|e| match e {
+      wav::WavError::Unsupported(msg) => Error::new(
+        Status::InvalidArg,
+        format!("Unsupported WAV format in {path}: {msg}"),
+      ),
+      wav::WavError::Invalid(msg) => Error::new(
+        Status::InvalidArg,
+        format!("Invalid WAV data in {path}: {msg}"),
+      ),
+      wav::WavError::Read(e) => Error::new(
+        Status::GenericFailure,
+        format!("WAV read error in {path}: {e}"),
+      ),
+    })?;
+    self
+      .add_audio(
+        &wav.samples,
+        wav.channels,
+        phoneme_name,
+        wav.sample_rate,
+        |_| Ok(()),
+        |_| Ok(()),
+      )
+      .map(|_| ())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::PI;
+
+  fn options() -> ProfileGeneratorOptions {
+    ProfileGeneratorOptions {
+      target_sample_rate: 16000,
+      mel_filter_bank_channels: 26,
+      ..Default::default()
+    }
+  }
+
+  fn sine(len: usize, freq: f32, sample_rate: u32) -> Float32Array {
+    Float32Array::new(
+      (0..len)
+        .map(|i| 0.5 * (2.0 * PI * freq * i as f32 / sample_rate as f32).sin())
+        .collect(),
+    )
+  }
+
+  fn finish_profile(generator: &mut ProfileGenerator) -> OutputJson {
+    serde_json::from_str(&generator.finish().unwrap()).unwrap()
+  }
+
+  fn frames(profile: &OutputJson, name: &str) -> Vec<Vec<f32>> {
+    profile
+      .mfccs
+      .iter()
+      .find(|entry| entry.name == name)
+      .map(|entry| {
+        entry
+          .mfcc_calibration_data_list
+          .iter()
+          .map(|data| data.array.clone())
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
+  #[test]
+  fn zero_sample_rate_is_rejected() {
+    let mut generator = ProfileGenerator::new(options()).unwrap();
+    let result = generator.add_sample_detailed(sine(4096, 440.0, 16000), "a".into(), 0, None);
+    assert!(result.is_err_and(|err| err.status == Status::InvalidArg));
+    assert!(extract_features(sine(1024, 440.0, 16000), 0, 16000, 26).is_err());
+    assert!(generator.get_phoneme_names().is_empty());
+  }
+
+  #[test]
+  fn cmn_before_and_after_trim() {
+    // 4 帧每帧的值都是 k，上限 2，保留最后两帧 3 和 4
+    let cmn_frames = |before: bool| {
+      let mut generator = ProfileGenerator::new(ProfileGeneratorOptions {
+        mfcc_data_count: Some(2),
+        cmn: Some(true),
+        cmn_before_trim: Some(before),
+        ..options()
+      })
+      .unwrap();
+      for k in 1..=4 {
+        let frame = Float32Array::new(vec![k as f32; MFCC_SIZE]);
+        assert!(generator.add_mfcc_frame(frame, "a".into()).unwrap());
+      }
+      frames(&finish_profile(&mut generator), "a")
+    };
+    // 裁剪之后求均值：3.5
+    assert_eq!(
+      cmn_frames(false),
+      vec![vec![-0.5; MFCC_SIZE], vec![0.5; MFCC_SIZE]]
+    );
+    // 裁剪之前对全部 4 帧求均值：2.5
+    assert_eq!(
+      cmn_frames(true),
+      vec![vec![0.5; MFCC_SIZE], vec![1.5; MFCC_SIZE]]
+    );
+  }
+
+  #[test]
+  fn window_resampling_to_empty_is_rejected() {
+    let mut generator = ProfileGenerator::new(ProfileGeneratorOptions {
+      sample_count: Some(2),
+      feature_type: Some(FeatureType::Spectrum),
+      spectrum_bins: Some(8),
+      ..options()
+    })
+    .unwrap();
+    // 48 kHz -> 16 kHz 每 3 个取 1 个，2 个样本的窗口抽取后为空
+    let result = generator.add_sample_detailed(sine(4800, 440.0, 48000), "a".into(), 48000, None);
+    assert!(result.is_err_and(|err| err.status == Status::InvalidArg));
+    let report = generator
+      .add_sample_detailed(sine(1600, 440.0, 16000), "a".into(), 16000, None)
+      .ok()
+      .unwrap();
+    assert_eq!(report.frames_processed, 800);
+  }
+
+  #[test]
+  fn large_mfcc_data_count_needs_opt_in() {
+    let with_cap = |count: u32, allow: Option<bool>| {
+      ProfileGenerator::new(ProfileGeneratorOptions {
+        mfcc_data_count: Some(count),
+        allow_large_capacity: allow,
+        ..options()
+      })
+    };
+    assert!(with_cap(u32::MAX, None).is_err_and(|err| err.status == Status::InvalidArg));
+    assert!(with_cap(MAX_MFCC_DATA_COUNT + 1, Some(false)).is_err());
+    assert!(with_cap(MAX_MFCC_DATA_COUNT, None).is_ok());
+    assert!(with_cap(u32::MAX, Some(true)).is_ok());
+  }
+
+  #[test]
+  fn merge_round_trip() {
+    let frame = |k: f32| Float32Array::new(vec![k; MFCC_SIZE]);
+    let capped = || {
+      ProfileGenerator::new(ProfileGeneratorOptions {
+        mfcc_data_count: Some(3),
+        ..options()
+      })
+      .unwrap()
+    };
+    let mut a = capped();
+    let mut b = capped();
+    a.add_mfcc_frame(frame(1.0), "a".into()).unwrap();
+    a.add_mfcc_frame(frame(2.0), "shared".into()).unwrap();
+    b.add_mfcc_frame(frame(3.0), "b".into()).unwrap();
+    for k in 4..7 {
+      b.add_mfcc_frame(frame(k as f32), "shared".into()).unwrap();
+    }
+    a.merge(&b).unwrap();
+    let profile = finish_profile(&mut a);
+    let names: Vec<&str> = profile.mfccs.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["a", "b", "shared"]);
+    assert_eq!(frames(&profile, "b"), vec![vec![3.0; MFCC_SIZE]]);
+    // 2 + [4, 5, 6] 裁剪到 3 帧，丢掉最旧的 2
+    let shared: Vec<f32> = frames(&profile, "shared").iter().map(|f| f[0]).collect();
+    assert_eq!(shared, [4.0, 5.0, 6.0]);
+
+    let mut other = ProfileGenerator::new(ProfileGeneratorOptions {
+      mel_filter_bank_channels: 30,
+      ..options()
+    })
+    .unwrap();
+    assert!(a.merge(&other).is_err());
+    assert!(other.finish().is_ok());
+  }
+
+  #[test]
+  fn merge_carries_sample_rates_and_cmn_sums() {
+    let strict = || {
+      ProfileGenerator::new(ProfileGeneratorOptions {
+        strict_sample_rate: Some(true),
+        mfcc_data_count: Some(1),
+        cmn: Some(true),
+        cmn_before_trim: Some(true),
+        ..options()
+      })
+      .unwrap()
+    };
+    let mut a = strict();
+    let mut b = strict();
+    b.add_sample_detailed(sine(4096, 440.0, 16000), "a".into(), 16000, None)
+      .ok()
+      .unwrap();
+    let mut c = strict();
+    c.add_sample_detailed(sine(4096, 440.0, 32000), "a".into(), 32000, None)
+      .ok()
+      .unwrap();
+    a.merge(&b).unwrap();
+    assert!(a
+      .merge(&c)
+      .is_err_and(|err| err.status == Status::InvalidArg));
+    let result = a.add_sample_detailed(sine(4096, 440.0, 32000), "a".into(), 32000, None);
+    assert!(result.is_err());
+    // b 的累计值包含裁剪到 1 帧之前的所有帧，合并后的均值与 b 相同
+    assert_eq!(a.cmn_sums["a"].mean(), b.cmn_sums["a"].mean());
+  }
+
+  #[test]
+  fn low_channel_resolution_keeps_frames() {
+    let mut generator = ProfileGenerator::new(ProfileGeneratorOptions {
+      sample_count: Some(128),
+      ..options()
+    })
+    .unwrap();
+    let report = generator
+      .add_sample_detailed(sine(1280, 440.0, 16000), "a".into(), 16000, None)
+      .ok()
+      .unwrap();
+    assert_eq!(report.frames_processed, 10);
+    assert_eq!(report.frames_dropped_nonfinite, 0);
+    let profile = finish_profile(&mut generator);
+    assert!(frames(&profile, "a")
+      .iter()
+      .flatten()
+      .all(|v| v.is_finite()));
+  }
+
+  #[test]
+  fn set_compare_method_changes_output() {
+    let mut generator = ProfileGenerator::new(options()).unwrap();
+    let frame = || Float32Array::new(vec![1.0; MFCC_SIZE]);
+    generator.add_mfcc_frame(frame(), "a".into()).unwrap();
+    assert_eq!(finish_profile(&mut generator).compare_method, 1);
+    generator.reset();
+    generator.set_compare_method(CompareMethod::CosineSimilarity);
+    generator.add_mfcc_frame(frame(), "a".into()).unwrap();
+    assert_eq!(finish_profile(&mut generator).compare_method, 2);
+  }
+
+  #[test]
+  fn compact_output_is_smaller() {
+    let output = |pretty: bool| {
+      let mut generator = ProfileGenerator::new(ProfileGeneratorOptions {
+        pretty: Some(pretty),
+        ..options()
+      })
+      .unwrap();
+      for name in ["a", "i", "u"] {
+        generator
+          .add_sample_detailed(sine(8192, 440.0, 16000), name.into(), 16000, None)
+          .ok()
+          .unwrap();
+      }
+      generator.finish().unwrap()
+    };
+    let (pretty, compact) = (output(true), output(false));
+    assert!(
+      compact.len() < pretty.len() / 2,
+      "{} vs {}",
+      compact.len(),
+      pretty.len()
+    );
+    let parsed: serde_json::Value = serde_json::from_str(&compact).unwrap();
+    assert_eq!(
+      parsed,
+      serde_json::from_str::<serde_json::Value>(&pretty).unwrap()
+    );
+  }
+
+  #[test]
+  fn output_order_is_independent_of_insertion_order() {
+    let output = |order: &[&str]| {
+      let mut generator = ProfileGenerator::new(options()).unwrap();
+      for &name in order {
+        let freq = 200.0 + 100.0 * name.len() as f32 + name.as_bytes()[0] as f32;
+        generator
+          .add_sample_detailed(sine(4096, freq, 16000), name.into(), 16000, None)
+          .ok()
+          .unwrap();
+      }
+      generator.finish().unwrap()
+    };
+    let first = output(&["u", "a", "sil", "o", "e", "i"]);
+    let second = output(&["i", "sil", "e", "a", "u", "o"]);
+    assert_eq!(first, second);
+    let profile: OutputJson = serde_json::from_str(&first).unwrap();
+    let names: Vec<&str> = profile.mfccs.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["a", "e", "i", "o", "sil", "u"]);
+  }
+
+  #[test]
+  fn delta_profile_round_trips_through_from_json() {
+    let mut generator = ProfileGenerator::new(ProfileGeneratorOptions {
+      compute_deltas: Some(true),
+      ..options()
+    })
+    .unwrap();
+    generator
+      .add_sample_detailed(sine(4096, 440.0, 16000), "a".into(), 16000, None)
+      .ok()
+      .unwrap();
+    let json = generator.finish().unwrap();
+    let profile: OutputJson = serde_json::from_str(&json).unwrap();
+    assert!(profile.compute_deltas);
+    assert_eq!(profile.mfcc_num, 2 * MFCC_SIZE);
+
+    let mut restored = ProfileGenerator::from_json(json).unwrap();
+    assert_eq!(restored.get_options().compute_deltas, Some(true));
+    assert_eq!(restored.feature_len(), MFCC_SIZE);
+    restored
+      .add_sample_detailed(sine(4096, 440.0, 16000), "a".into(), 16000, None)
+      .ok()
+      .unwrap();
+    let profile = finish_profile(&mut restored);
+    assert_eq!(profile.mfcc_num, 2 * MFCC_SIZE);
+    assert!(frames(&profile, "a")
+      .iter()
+      .all(|frame| frame.len() == 2 * MFCC_SIZE));
+  }
+
+  #[test]
+  fn cmn_orderings_treat_deltas_alike() {
+    // 不触发裁剪时两种统计范围的均值相同，输出（含差分部分）也应该相同
+    let output = |before: bool| {
+      let mut generator = ProfileGenerator::new(ProfileGeneratorOptions {
+        compute_deltas: Some(true),
+        cmn: Some(true),
+        cmn_before_trim: Some(before),
+        ..options()
+      })
+      .unwrap();
+      for k in [1.0, 2.0, 4.0] {
+        let frame = Float32Array::new(vec![k; MFCC_SIZE]);
+        generator.add_mfcc_frame(frame, "a".into()).unwrap();
+      }
+      frames(&finish_profile(&mut generator), "a")
+    };
+    let after = output(false);
+    assert_eq!(after, output(true));
+    // 差分保持未减均值的中心差分：(2 - 1), (4 - 1) / 2, (4 - 2)
+    let deltas: Vec<f32> = after.iter().map(|frame| frame[MFCC_SIZE]).collect();
+    assert_eq!(deltas, [1.0, 1.5, 2.0]);
+  }
+
+  #[test]
+  fn phoneme_names_are_trimmed() {
+    let mut generator = ProfileGenerator::new(options()).unwrap();
+    let audio = || sine(4096, 440.0, 16000);
+    for name in ["", "   ", "\t\n"] {
+      let result = generator.add_sample_detailed(audio(), name.into(), 16000, None);
+      assert!(result.is_err_and(|err| err.status == Status::InvalidArg));
+    }
+    generator
+      .add_sample_detailed(audio(), " A ".into(), 16000, None)
+      .ok()
+      .unwrap();
+    assert_eq!(generator.get_phoneme_names(), ["A"]);
+    assert_eq!(generator.get_sample_count(" A ".into()), 4);
+    assert_eq!(generator.get_mfccs(" A ".into()).len(), 4);
+    // 名字区分大小写
+    assert_eq!(generator.get_sample_count("a".into()), 0);
+    generator
+      .combine_phonemes(vec![" A ".into()], "B".into(), None)
+      .unwrap();
+    assert!(generator.remove_phoneme(" A ".into()));
+    assert_eq!(generator.get_phoneme_names(), ["B"]);
+  }
+
+  #[test]
+  fn lpf_range_is_checked_against_input_rate() {
+    let mut generator = ProfileGenerator::new(ProfileGeneratorOptions {
+      lpf_transition_range: Some(2000.0),
+      ..options()
+    })
+    .unwrap();
+    // 8 kHz 输入的截止频率为 4000 Hz，2000 Hz 的过渡带可用；4 kHz 输入只有 2000 Hz，不可用
+    assert!(generator
+      .add_sample_detailed(sine(4096, 440.0, 8000), "a".into(), 8000, None)
+      .is_ok());
+    let result = generator.add_sample_detailed(sine(4096, 440.0, 4000), "b".into(), 4000, None);
+    assert!(result.is_err_and(|err| err.status == Status::InvalidArg));
+    let frames = vec![sine(1024, 440.0, 4000)];
+    assert!(generator.add_frames(frames, "b".into(), 4000).is_err());
+    assert!(generator.compare(sine(4096, 440.0, 4000), 4000).is_err());
+    assert_eq!(generator.get_phoneme_names(), ["a"]);
+  }
+
+  #[test]
+  fn tiny_sample_rate_fails_loudly() {
+    // 1000 Hz 输入的截止频率只有 500 Hz，等于默认过渡带，低通无法设计
+    let mut generator = ProfileGenerator::new(options()).unwrap();
+    let result = generator.add_sample_detailed(sine(4096, 100.0, 1000), "a".into(), 1000, None);
+    assert!(result.is_err_and(|err| err.status == Status::InvalidArg));
+    assert!(generator.get_phoneme_names().is_empty());
+    assert!(extract_features(sine(1024, 100.0, 1000), 1000, 16000, 26).is_err());
+    assert!(extract_features(sine(1024, 100.0, 1200), 1200, 16000, 26).is_ok());
+  }
+
+  #[test]
+  fn keep_c0_prepends_c0_and_round_trips() {
+    let mut plain = ProfileGenerator::new(options()).unwrap();
+    let mut with_c0 = ProfileGenerator::new(ProfileGeneratorOptions {
+      keep_c0: Some(true),
+      ..options()
+    })
+    .unwrap();
+    for generator in [&mut plain, &mut with_c0] {
+      generator
+        .add_sample_detailed(sine(4096, 440.0, 16000), "a".into(), 16000, None)
+        .ok()
+        .unwrap();
+    }
+    assert!(!finish_profile(&mut plain).keep_c0);
+    let json = with_c0.finish().unwrap();
+    let profile: OutputJson = serde_json::from_str(&json).unwrap();
+    assert!(profile.keep_c0);
+    assert_eq!(profile.mfcc_num, MFCC_SIZE + 1);
+    // 去掉 c0 之后与不保留 c0 时的系数相同
+    plain
+      .add_sample_detailed(sine(4096, 440.0, 16000), "a".into(), 16000, None)
+      .ok()
+      .unwrap();
+    let expected = frames(&finish_profile(&mut plain), "a");
+    let actual = frames(&profile, "a");
+    assert_eq!(actual.len(), expected.len());
+    for (frame, plain) in actual.iter().zip(&expected) {
+      assert_eq!(&frame[1..], plain.as_slice());
+    }
+
+    let restored = ProfileGenerator::from_json(json).unwrap();
+    assert_eq!(restored.get_options().keep_c0, Some(true));
+    assert_eq!(restored.feature_len(), MFCC_SIZE + 1);
+
+    // 第一个元素就是完整 DCT 的第 0 项
+    let config = mfcc::MfccConfig {
+      keep_c0: true,
+      ..mfcc::MfccConfig::new(16000, 26)
+    };
+    let mut pool = mfcc::MfccBufferPool::new();
+    let (mut frame, mut out) = (sine(1024, 440.0, 16000).to_vec(), Vec::new());
+    mfcc::extract_mfcc(&mut frame, 0, 16000, &config, &mut pool, &mut out);
+    let mel = pool.mel_spectrum(&config);
+    let mut cepstrum = vec![0.0; mel.len()];
+    algorithm::dct(&mel, &mut cepstrum, mel.len(), false);
+    assert_eq!(out.len(), MFCC_SIZE + 1);
+    assert_eq!(out[0].to_bits(), cepstrum[0].to_bits());
+    assert_eq!(out[1..], cepstrum[1..=MFCC_SIZE]);
+  }
+
+  #[test]
+  fn unlimited_retention_keeps_100_frames() {
+    let mut generator = ProfileGenerator::new(ProfileGeneratorOptions {
+      mfcc_data_count: Some(8),
+      retention_policy: Some(RetentionPolicy::Unlimited),
+      ..options()
+    })
+    .unwrap();
+    let input: Vec<_> = (0..100)
+      .map(|i| sine(1024, 200.0 + 10.0 * i as f32, 16000))
+      .collect();
+    assert_eq!(generator.add_frames(input, "a".into(), 16000).unwrap(), 100);
+    assert_eq!(generator.get_sample_count("a".into()), 100);
+    let profile = finish_profile(&mut generator);
+    assert_eq!(profile.mfcc_data_count, 100);
+    assert_eq!(frames(&profile, "a").len(), 100);
+  }
+
+  #[test]
+  fn ulipsync_exact_matches_defaults_and_rejects_conflicts() {
+    let mut plain = ProfileGenerator::new(options()).unwrap();
+    let mut exact = ProfileGenerator::new(ProfileGeneratorOptions {
+      ulipsync_exact: Some(true),
+      ..options()
+    })
+    .unwrap();
+    for generator in [&mut plain, &mut exact] {
+      generator
+        .add_sample_detailed(sine(4096, 440.0, 44100), "a".into(), 44100, None)
+        .ok()
+        .unwrap();
+    }
+    let expected = frames(&finish_profile(&mut plain), "a");
+    assert_eq!(frames(&finish_profile(&mut exact), "a"), expected);
+
+    let conflicting = ProfileGenerator::new(ProfileGeneratorOptions {
+      ulipsync_exact: Some(true),
+      use_slaney_mel: Some(true),
+      ..options()
+    });
+    assert!(conflicting.is_err_and(|err| err.status == Status::InvalidArg));
+  }
+
+  #[test]
+  fn filter_profile_checks_format_version() {
+    let mut generator = ProfileGenerator::new(options()).unwrap();
+    generator
+      .add_sample_detailed(sine(4096, 440.0, 16000), "a".into(), 16000, None)
+      .ok()
+      .unwrap();
+    let json = generator.finish().unwrap();
+    assert!(ProfileGenerator::filter_profile(json.clone(), vec!["a".into()]).is_ok());
+    let current = format!("\"formatVersion\":{FORMAT_VERSION}");
+    for version in [FORMAT_VERSION + 1, FORMAT_VERSION - 1] {
+      let other = json.replace(&current, &format!("\"formatVersion\":{version}"));
+      let result = ProfileGenerator::filter_profile(other, vec!["a".into()]);
+      assert!(result.is_err_and(|err| err.status == Status::InvalidArg));
+    }
   }
 }
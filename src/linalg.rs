@@ -0,0 +1,65 @@
+// 单边 Jacobi（Hestenes）SVD，只求奇异值。矩阵按行给出，列数就是特征维度，规模很小
+pub fn singular_values(rows: &[&[f32]]) -> Vec<f64> {
+  let cols = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+  // 按列存储，方便做列旋转
+  let mut a: Vec<Vec<f64>> = (0..cols)
+    .map(|j| {
+      rows
+        .iter()
+        .map(|row| row.get(j).copied().unwrap_or(0.0) as f64)
+        .collect()
+    })
+    .collect();
+
+  const MAX_SWEEPS: usize = 60;
+  const TOLERANCE: f64 = 1e-12;
+  for _ in 0..MAX_SWEEPS {
+    let mut rotated = false;
+    for p in 0..cols {
+      for q in p + 1..cols {
+        let alpha: f64 = a[p].iter().map(|x| x * x).sum();
+        let beta: f64 = a[q].iter().map(|x| x * x).sum();
+        let gamma: f64 = a[p].iter().zip(&a[q]).map(|(x, y)| x * y).sum();
+        if gamma == 0.0 || gamma.abs() <= TOLERANCE * (alpha * beta).sqrt() {
+          continue;
+        }
+        rotated = true;
+        let zeta = (beta - alpha) / (2.0 * gamma);
+        let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+        let c = 1.0 / (1.0 + t * t).sqrt();
+        let s = c * t;
+        let (left, right) = a.split_at_mut(q);
+        for (x, y) in left[p].iter_mut().zip(right[0].iter_mut()) {
+          let (xp, xq) = (*x, *y);
+          *x = c * xp - s * xq;
+          *y = s * xp + c * xq;
+        }
+      }
+    }
+    if !rotated {
+      break;
+    }
+  }
+
+  let mut values: Vec<f64> = a
+    .iter()
+    .map(|col| col.iter().map(|x| x * x).sum::<f64>().sqrt())
+    .collect();
+  values.sort_by(|x, y| y.total_cmp(x));
+  values
+}
+
+// 奇异值归一化后的熵取 exp（Roy & Vetterli 有效秩），范围 [1, min(n, d)]，全零矩阵为 0
+pub fn effective_rank(singular_values: &[f64]) -> f64 {
+  let total: f64 = singular_values.iter().sum();
+  if total <= 0.0 {
+    return 0.0;
+  }
+  let entropy: f64 = singular_values
+    .iter()
+    .map(|s| s / total)
+    .filter(|&p| p > 0.0)
+    .map(|p| -p * p.ln())
+    .sum();
+  entropy.exp()
+}
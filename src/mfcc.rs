@@ -1,67 +1,290 @@
-use crate::algorithm;
+use crate::algorithm::{self, Real};
 use crate::MFCC_SIZE;
-use rustfft::num_complex::Complex32;
+use crate::{FeatureType, LogBase, MelNorm, NormalizeMode, ResampleQuality, Stage, WindowFunction};
+use realfft::num_complex::Complex;
+use realfft::FftNum;
+use std::f32::consts::FRAC_1_SQRT_2;
 
+// 抗混叠低通的过渡带宽（Hz）
+pub const DEFAULT_LPF_RANGE: f32 = 500.0;
+
+#[derive(Clone)]
+pub struct MfccConfig {
+  pub target_sample_rate: u32,
+  pub mel_filter_bank_channels: usize,
+  pub feature_type: FeatureType,
+  pub spectrum_bins: Option<usize>,
+  pub log_base: LogBase,
+  pub stages: [Stage; 3],
+  // 保留的倒谱系数个数（从 c1 开始）
+  pub mfcc_num: usize,
+  pub slaney_mel: bool,
+  // mel 滤波器覆盖的频率范围（Hz），mel_fmax 为 None 时取 target_sample_rate / 2
+  pub mel_fmin: f32,
+  pub mel_fmax: Option<f32>,
+  pub mel_norm: MelNorm,
+  // mel 滤波改走与 uLipSync C# 版逐行对应的实现，构造时已排除与之冲突的选项
+  pub ulipsync_exact: bool,
+  pub window: WindowFunction,
+  pub normalize: NormalizeMode,
+  // 归一化目标峰值，0 表示跳过归一化
+  pub normalize_peak: f32,
+  pub remove_dc: bool,
+  // 重采样之后、预加重等步骤之前的一阶高通截止频率（Hz），None 表示不做
+  pub highpass_cutoff: Option<f32>,
+  // 重采样之后叠加的 TPDF 抖动幅度（满幅为 1），0 表示不加
+  pub dither: f32,
+  pub pre_emphasis: f32,
+  // 正弦倒谱提升参数 L，0 表示不做
+  pub lifter: f32,
+  pub dct_ortho: bool,
+  // 为 true 时输出以 c0 开头，共 mfcc_num + 1 个系数
+  pub keep_c0: bool,
+  pub lpf_range: f32,
+  pub resample_quality: ResampleQuality,
+  // FFT、mel 滤波、取对数和 DCT 用 f64 计算
+  pub high_precision: bool,
+  // FFT 之前把重采样后的窗口补零到 2 的幂
+  pub pad_fft_to_pow2: bool,
+}
+
+pub const DEFAULT_STAGES: [Stage; 3] = [Stage::PreEmphasis, Stage::Window, Stage::Normalize];
+
+impl MfccConfig {
+  pub fn new(target_sample_rate: u32, mel_filter_bank_channels: usize) -> Self {
+    Self {
+      target_sample_rate,
+      mel_filter_bank_channels,
+      feature_type: FeatureType::Mfcc,
+      spectrum_bins: None,
+      log_base: LogBase::Db10,
+      stages: DEFAULT_STAGES,
+      mfcc_num: MFCC_SIZE,
+      slaney_mel: false,
+      mel_fmin: 0.0,
+      mel_fmax: None,
+      mel_norm: MelNorm::SlaneyArea,
+      ulipsync_exact: false,
+      window: WindowFunction::Hamming,
+      normalize: NormalizeMode::Peak,
+      normalize_peak: 1.0,
+      remove_dc: false,
+      highpass_cutoff: None,
+      dither: 0.0,
+      pre_emphasis: 0.97,
+      lifter: 0.0,
+      dct_ortho: false,
+      keep_c0: false,
+      lpf_range: DEFAULT_LPF_RANGE,
+      resample_quality: ResampleQuality::Low,
+      high_precision: false,
+      pad_fft_to_pow2: false,
+    }
+  }
+}
+
+#[derive(Default)]
 pub struct MfccBufferPool {
+  lpf_input: Vec<f32>,
+  lpf_kernel: Vec<f32>,
   downsample: Vec<f32>,
-  fft_complex: Vec<Complex32>,
-  spectrum: Vec<f32>,
-  mel_spectrum: Vec<f32>,
+  // FFT 之后的缓冲，highPrecision 时用 f64 那一份
+  single: Spectral<f32>,
+  double: Spectral<f64>,
+  downsample64: Vec<f64>,
   cepstrum: Vec<f32>,
+  // 窗系数按 (窗类型, 长度) 缓存，只在变化时重新计算
+  window: Vec<f32>,
+  window_key: Option<(WindowFunction, usize)>,
 }
 
 impl MfccBufferPool {
   pub fn new() -> Self {
-    Self {
-      downsample: Vec::new(),
-      fft_complex: Vec::new(),
-      spectrum: Vec::new(),
-      mel_spectrum: Vec::new(),
-      cepstrum: Vec::new(),
+    Self::default()
+  }
+
+  // 最近一帧取对数之后、DCT 之前的 mel 谱（Spectrum 特征不经过这一步）
+  pub fn mel_spectrum(&self, config: &MfccConfig) -> Vec<f32> {
+    if config.high_precision {
+      self.double.mel.iter().map(|&v| v as f32).collect()
+    } else {
+      self.single.mel.clone()
+    }
+  }
+}
+
+#[derive(Default)]
+struct Spectral<T: FftNum> {
+  plan: algorithm::FftPlan<T>,
+  complex: Vec<Complex<T>>,
+  spectrum: Vec<T>,
+  mel: Vec<T>,
+  cepstrum: Vec<T>,
+}
+
+// 频谱 -> Mel滤波 -> dB（或 ln / log2）-> DCT，在 T 精度下计算。Spectrum 特征直接写入 out
+// 并返回 false；否则把全部倒谱系数（含 c0）转成 f32 写入 cepstrum
+fn analyze<T: Real>(
+  data: &mut [T],
+  config: &MfccConfig,
+  s: &mut Spectral<T>,
+  cepstrum: &mut Vec<f32>,
+  out: &mut Vec<f32>,
+) -> bool {
+  let target_sample_rate = config.target_sample_rate;
+  let mel_filter_bank_channels = config.mel_filter_bank_channels;
+  algorithm::fft(data, &mut s.plan, &mut s.complex, &mut s.spectrum);
+  if let FeatureType::Spectrum = config.feature_type {
+    out.clear();
+    match config.spectrum_bins {
+      Some(bins) => algorithm::pool_bins(&s.spectrum, bins, out),
+      None => out.extend(s.spectrum.iter().map(|v| v.narrow())),
     }
+    return false;
+  }
+  if s.mel.len() != mel_filter_bank_channels {
+    s.mel.resize(mel_filter_bank_channels, T::zero());
+  }
+  if config.ulipsync_exact {
+    algorithm::mel_filter_bank_ulipsync(
+      &s.spectrum,
+      target_sample_rate as f32,
+      mel_filter_bank_channels,
+      &mut s.mel,
+    );
+  } else {
+    algorithm::mel_filter_bank(
+      &s.spectrum,
+      target_sample_rate as f32,
+      mel_filter_bank_channels,
+      (
+        config.mel_fmin,
+        config.mel_fmax.unwrap_or(target_sample_rate as f32 / 2.0),
+      ),
+      config.slaney_mel,
+      config.mel_norm,
+      &mut s.mel,
+    );
+  }
+  match config.log_base {
+    LogBase::Db10 => algorithm::power_to_db(&mut s.mel),
+    LogBase::Ln => algorithm::power_to_ln(&mut s.mel),
+    LogBase::Log2 => algorithm::power_to_log2(&mut s.mel),
+  }
+  // c0 会被跳过，只需多算一个
+  let num_coeffs = (config.mfcc_num + 1).min(mel_filter_bank_channels);
+  if s.cepstrum.len() != num_coeffs {
+    s.cepstrum.resize(num_coeffs, T::zero());
   }
+  algorithm::dct(&s.mel, &mut s.cepstrum, num_coeffs, config.dct_ortho);
+  cepstrum.clear();
+  cepstrum.extend(s.cepstrum.iter().map(|v| v.narrow()));
+  true
 }
 
+// 低通 FIR 的预热长度：输出样本前面要有 blen - 1 个输入样本，卷积才是完整的
+pub fn filter_warmup(input_sample_rate: u32, config: &MfccConfig) -> usize {
+  algorithm::low_pass_filter_len(input_sample_rate as f32, config.lpf_range).saturating_sub(1)
+}
+
+// input 前 warmup 个样本只参与低通，不进入后续分析
 pub fn extract_mfcc(
   input: &mut [f32],
+  warmup: usize,
   input_sample_rate: u32,
-  target_sample_rate: u32,
-  mel_filter_bank_channels: usize,
+  config: &MfccConfig,
   pool: &mut MfccBufferPool,
   out: &mut Vec<f32>,
 ) {
-  const RANGE: f32 = 500.0;
-  let cutoff = target_sample_rate as f32 / 2.0;
+  let target_sample_rate = config.target_sample_rate;
+  // 升采样时输入本身没有高于其奈奎斯特频率的成分，截止频率按较低的一方取。
+  // 截止频率必须大于 lpf_range，调用方在提取之前检查
+  let cutoff = target_sample_rate.min(input_sample_rate) as f32 / 2.0;
 
-  // 低通 + 降采样 + 预加重 + 汉明窗 + 归一化
-  algorithm::low_pass_filter(input, input_sample_rate as f32, cutoff, RANGE);
-  algorithm::downsample(
+  // 低通 + 重采样 +（可选高通）+ 预加重 + 汉明窗 + 归一化（后三步顺序可配置）
+  algorithm::low_pass_filter(
     input,
+    input_sample_rate as f32,
+    cutoff,
+    config.lpf_range,
+    &mut pool.lpf_input,
+    &mut pool.lpf_kernel,
+  );
+  algorithm::resample(
+    &input[warmup..],
     input_sample_rate,
     target_sample_rate,
+    config.resample_quality,
     &mut pool.downsample,
   );
-  algorithm::pre_emphasis(&mut pool.downsample, 0.97);
-  algorithm::hamming(&mut pool.downsample);
-  algorithm::normalize(&mut pool.downsample, 1.0);
+  if config.dither > 0.0 {
+    algorithm::dither(&mut pool.downsample, config.dither);
+  }
+  if let Some(cutoff) = config.highpass_cutoff {
+    algorithm::high_pass_filter(&mut pool.downsample, target_sample_rate as f32, cutoff);
+  }
+  for stage in config.stages {
+    match stage {
+      Stage::PreEmphasis => algorithm::pre_emphasis(&mut pool.downsample, config.pre_emphasis),
+      Stage::Window => {
+        let key = (config.window, pool.downsample.len());
+        if pool.window_key != Some(key) {
+          algorithm::window(key.0, key.1, &mut pool.window);
+          pool.window_key = Some(key);
+        }
+        algorithm::apply_window(&mut pool.downsample, &pool.window);
+      }
+      Stage::Normalize if config.normalize_peak == 0.0 => {}
+      Stage::Normalize => match config.normalize {
+        NormalizeMode::Peak => algorithm::normalize(&mut pool.downsample, config.normalize_peak),
+        // 目标取同峰值正弦的 RMS，纯音的结果与峰值归一化相同
+        NormalizeMode::Rms => {
+          algorithm::normalize_rms(&mut pool.downsample, config.normalize_peak * FRAC_1_SQRT_2)
+        }
+      },
+    }
+  }
 
-  // 频谱 -> Mel滤波 -> dB -> DCT -> MFCC（跳过第0项）
-  algorithm::fft(&pool.downsample, &mut pool.fft_complex, &mut pool.spectrum);
-  if pool.mel_spectrum.len() != mel_filter_bank_channels {
-    pool.mel_spectrum.resize(mel_filter_bank_channels, 0.0);
-  }
-  algorithm::mel_filter_bank(
-    &pool.spectrum,
-    target_sample_rate as f32,
-    mel_filter_bank_channels,
-    &mut pool.mel_spectrum,
-  );
-  algorithm::power_to_db(&mut pool.mel_spectrum);
-  if pool.cepstrum.len() != mel_filter_bank_channels {
-    pool.cepstrum.resize(mel_filter_bank_channels, 0.0);
+  if config.remove_dc {
+    algorithm::remove_dc(&mut pool.downsample);
+  }
+  // 加窗之后再补零，只是频率方向插值更密；mel 滤波器按谱长度重新计算频率分辨率
+  if config.pad_fft_to_pow2 {
+    let len = pool.downsample.len().next_power_of_two();
+    pool.downsample.resize(len, 0.0);
+  }
+
+  let cepstral = if config.high_precision {
+    pool.downsample64.clear();
+    pool
+      .downsample64
+      .extend(pool.downsample.iter().map(|&x| x as f64));
+    analyze(
+      &mut pool.downsample64,
+      config,
+      &mut pool.double,
+      &mut pool.cepstrum,
+      out,
+    )
+  } else {
+    analyze(
+      &mut pool.downsample,
+      config,
+      &mut pool.single,
+      &mut pool.cepstrum,
+      out,
+    )
+  };
+  if !cepstral {
+    return;
+  }
+  if config.lifter > 0.0 {
+    algorithm::lifter(&mut pool.cepstrum, config.lifter);
   }
-  algorithm::dct(&pool.mel_spectrum, &mut pool.cepstrum);
   out.clear();
-  out.reserve(MFCC_SIZE);
-  out.extend(pool.cepstrum.iter().skip(1).take(MFCC_SIZE));
+  if config.keep_c0 {
+    out.extend_from_slice(&pool.cepstrum);
+  } else {
+    out.extend(pool.cepstrum.iter().skip(1).take(config.mfcc_num));
+  }
 }
@@ -0,0 +1,80 @@
+use crate::{FeatureType, MfccCalibrationData};
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufWriter;
+
+// 由 MFCC（c1..cN，keep_c0 时为 c0..cN）做 DCT-III 逆变换，近似还原 log-mel 包络。
+// 没有 c0 时只能得到相对形状，绝对电平已经丢失。
+fn reconstruct_log_mel(mfcc: &[f32], keep_c0: bool, mel_channels: usize, out: &mut [f32]) {
+  let n = mel_channels as f32;
+  let a = PI / n;
+  let first = if keep_c0 { 0 } else { 1 };
+  for (j, value) in out.iter_mut().enumerate().take(mel_channels) {
+    let mut sum = 0.0;
+    for (k, c) in mfcc.iter().enumerate() {
+      let i = k + first;
+      let weight = if i == 0 { 0.5 } else { 1.0 };
+      sum += weight * c * ((j as f32 + 0.5) * i as f32 * a).cos();
+    }
+    *value = 2.0 * sum / n;
+  }
+}
+
+// 宽 = 帧数，高 = mel 通道数（或谱长度），低频在底部。
+// 整张图按 min/max 线性映射到 0..255，越亮能量越高；全图相同时输出全黑。
+// Spectrum 特征直接取幅度的 dB。
+pub fn write_png(
+  frames: &[MfccCalibrationData],
+  feature_type: FeatureType,
+  keep_c0: bool,
+  mel_channels: usize,
+  path: &str,
+) -> Result<(), String> {
+  let width = frames.len();
+  let height = match feature_type {
+    FeatureType::Mfcc => mel_channels,
+    FeatureType::Spectrum => frames.iter().map(|f| f.array.len()).max().unwrap_or(0),
+  };
+  let mut log_mel = vec![0.0; width * height];
+  for (x, frame) in frames.iter().enumerate() {
+    let column = &mut log_mel[x * height..(x + 1) * height];
+    match feature_type {
+      FeatureType::Mfcc => reconstruct_log_mel(&frame.array, keep_c0, mel_channels, column),
+      FeatureType::Spectrum => {
+        for (value, &mag) in column.iter_mut().zip(&frame.array) {
+          *value = 20.0 * mag.max(f32::MIN_POSITIVE).log10();
+        }
+      }
+    }
+  }
+
+  let (min, max) = log_mel
+    .iter()
+    .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+      (lo.min(v), hi.max(v))
+    });
+  let scale = if max - min > f32::EPSILON {
+    255.0 / (max - min)
+  } else {
+    0.0
+  };
+
+  let mut pixels = vec![0u8; width * height];
+  for x in 0..width {
+    for band in 0..height {
+      let y = height - 1 - band;
+      let v = (log_mel[x * height + band] - min) * scale;
+      pixels[y * width + x] = v.round().clamp(0.0, 255.0) as u8;
+    }
+  }
+
+  let file = File::create(path).map_err(|e| e.to_string())?;
+  let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+  encoder.set_color(png::ColorType::Grayscale);
+  encoder.set_depth(png::BitDepth::Eight);
+  let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+  writer
+    .write_image_data(&pixels)
+    .map_err(|e| e.to_string())?;
+  writer.finish().map_err(|e| e.to_string())
+}
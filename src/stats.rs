@@ -0,0 +1,208 @@
+use crate::MfccCalibrationData;
+
+#[derive(Clone, Default)]
+pub struct RunningMean {
+  sum: Vec<f64>,
+  count: usize,
+}
+
+impl RunningMean {
+  pub fn push(&mut self, frame: &[f32]) {
+    if self.sum.len() < frame.len() {
+      self.sum.resize(frame.len(), 0.0);
+    }
+    for (sum, &value) in self.sum.iter_mut().zip(frame) {
+      *sum += value as f64;
+    }
+    self.count += 1;
+  }
+
+  pub fn merge(&mut self, other: &RunningMean) {
+    if self.sum.len() < other.sum.len() {
+      self.sum.resize(other.sum.len(), 0.0);
+    }
+    for (sum, value) in self.sum.iter_mut().zip(&other.sum) {
+      *sum += value;
+    }
+    self.count += other.count;
+  }
+
+  pub fn mean(&self) -> Vec<f64> {
+    if self.count == 0 {
+      return Vec::new();
+    }
+    let n = self.count as f64;
+    self.sum.iter().map(|sum| sum / n).collect()
+  }
+}
+
+pub fn mean(frames: &[MfccCalibrationData]) -> Vec<f64> {
+  let mut acc = RunningMean::default();
+  for frame in frames {
+    acc.push(&frame.array);
+  }
+  acc.mean()
+}
+
+pub fn subtract_mean(frames: &mut [MfccCalibrationData], mean: &[f64]) {
+  for frame in frames {
+    for (value, m) in frame.array.iter_mut().zip(mean) {
+      *value = (*value as f64 - m) as f32;
+    }
+  }
+}
+
+// 在每帧后面拼上相邻帧的中心差分，首尾帧用单边差分；只有一帧时差分为 0
+pub fn append_deltas(frames: &mut [MfccCalibrationData]) {
+  let deltas: Vec<Vec<f32>> = (0..frames.len())
+    .map(|t| {
+      let (lo, hi) = (t.saturating_sub(1), (t + 1).min(frames.len() - 1));
+      let span = (hi - lo).max(1) as f32;
+      let (prev, next) = (&frames[lo].array, &frames[hi].array);
+      next.iter().zip(prev).map(|(n, p)| (n - p) / span).collect()
+    })
+    .collect();
+  for (frame, delta) in frames.iter_mut().zip(deltas) {
+    frame.array.extend(delta);
+  }
+}
+
+fn squared_distance(a: &[f32], b: &[f64]) -> f64 {
+  a.iter().zip(b).map(|(&x, y)| (x as f64 - y).powi(2)).sum()
+}
+
+const KMEANS_ITERATIONS: usize = 10;
+
+// 用 k-means（最远点初始化，固定迭代次数，结果确定）把帧聚成 k 类，
+// 每类取离中心最近的一帧，返回按原顺序排列的下标
+pub fn representative(frames: &[MfccCalibrationData], k: usize) -> Vec<usize> {
+  if frames.len() <= k {
+    return (0..frames.len()).collect();
+  }
+  let as_f64 = |i: usize| -> Vec<f64> { frames[i].array.iter().map(|&v| v as f64).collect() };
+
+  let global = mean(frames);
+  let first = (0..frames.len())
+    .min_by(|&a, &b| {
+      squared_distance(&frames[a].array, &global)
+        .total_cmp(&squared_distance(&frames[b].array, &global))
+    })
+    .unwrap_or(0);
+  let mut centroids = vec![as_f64(first)];
+  let mut nearest: Vec<f64> = frames
+    .iter()
+    .map(|f| squared_distance(&f.array, &centroids[0]))
+    .collect();
+  while centroids.len() < k {
+    let far = (0..frames.len())
+      .max_by(|&a, &b| nearest[a].total_cmp(&nearest[b]))
+      .unwrap_or(0);
+    let c = as_f64(far);
+    for (d, f) in nearest.iter_mut().zip(frames) {
+      *d = d.min(squared_distance(&f.array, &c));
+    }
+    centroids.push(c);
+  }
+
+  let mut labels = vec![0; frames.len()];
+  for _ in 0..KMEANS_ITERATIONS {
+    for (label, f) in labels.iter_mut().zip(frames) {
+      *label = (0..k)
+        .min_by(|&a, &b| {
+          squared_distance(&f.array, &centroids[a])
+            .total_cmp(&squared_distance(&f.array, &centroids[b]))
+        })
+        .unwrap_or(0);
+    }
+    let mut sums = vec![RunningMean::default(); k];
+    for (&label, f) in labels.iter().zip(frames) {
+      sums[label].push(&f.array);
+    }
+    for (c, sum) in centroids.iter_mut().zip(&sums) {
+      // 空簇保留原来的中心
+      if sum.count > 0 {
+        *c = sum.mean();
+      }
+    }
+  }
+
+  let mut medoids: Vec<usize> = (0..k)
+    .filter_map(|c| {
+      (0..frames.len())
+        .filter(|&i| labels[i] == c)
+        .min_by(|&a, &b| {
+          squared_distance(&frames[a].array, &centroids[c])
+            .total_cmp(&squared_distance(&frames[b].array, &centroids[c]))
+        })
+    })
+    .collect();
+  medoids.sort_unstable();
+  medoids
+}
+
+// 逐系数的总体方差
+pub fn variance(frames: &[MfccCalibrationData], mean: &[f64]) -> Vec<f64> {
+  let mut var = vec![0.0; mean.len()];
+  if frames.is_empty() {
+    return var;
+  }
+  for frame in frames {
+    for ((v, &value), m) in var.iter_mut().zip(&frame.array).zip(mean) {
+      *v += (value as f64 - m).powi(2);
+    }
+  }
+  let n = frames.len() as f64;
+  var.iter_mut().for_each(|v| *v /= n);
+  var
+}
+
+// 标准差接近 0 的系数只减均值，不做除法
+const MIN_STD_DEV: f64 = 1e-6;
+
+// 逐系数减均值、除以标准差（总体标准差）
+pub fn standardize(frames: &mut [MfccCalibrationData]) {
+  if frames.is_empty() {
+    return;
+  }
+  let mean = mean(frames);
+  let std_dev: Vec<f64> = variance(frames, &mean).iter().map(|v| v.sqrt()).collect();
+  scale_frames(frames.iter_mut(), &mean, &std_dev);
+}
+
+// 与 standardize 相同，但统计量取自所有音素的帧（全局 CMVN）
+pub fn cmvn(frames: &mut [&mut MfccCalibrationData]) {
+  if frames.is_empty() {
+    return;
+  }
+  let mut acc = RunningMean::default();
+  for frame in frames.iter() {
+    acc.push(&frame.array);
+  }
+  let mean = acc.mean();
+  let mut var = vec![0.0; mean.len()];
+  for frame in frames.iter() {
+    for ((v, &value), m) in var.iter_mut().zip(&frame.array).zip(&mean) {
+      *v += (value as f64 - m).powi(2);
+    }
+  }
+  let n = frames.len() as f64;
+  let std_dev: Vec<f64> = var.iter().map(|v| (v / n).sqrt()).collect();
+  scale_frames(frames.iter_mut().map(|frame| &mut **frame), &mean, &std_dev);
+}
+
+fn scale_frames<'a>(
+  frames: impl Iterator<Item = &'a mut MfccCalibrationData>,
+  mean: &[f64],
+  std_dev: &[f64],
+) {
+  for frame in frames {
+    for ((value, m), s) in frame.array.iter_mut().zip(mean).zip(std_dev) {
+      let centered = *value as f64 - m;
+      *value = if *s > MIN_STD_DEV {
+        centered / s
+      } else {
+        centered
+      } as f32;
+    }
+  }
+}
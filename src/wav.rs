@@ -0,0 +1,144 @@
+use crate::audio;
+use hound::{SampleFormat, WavReader};
+
+pub struct WavData {
+  // 交错多声道，[-1, 1)
+  pub samples: Vec<f32>,
+  pub channels: usize,
+  pub sample_rate: u32,
+}
+
+pub enum WavError {
+  Unsupported(String),
+  // 文件能读，但内容不完整
+  Invalid(String),
+  Read(hound::Error),
+}
+
+const NOT_WHOLE_FRAMES: &str = "WAV data length is not a multiple of the channel count";
+
+impl From<hound::Error> for WavError {
+  fn from(e: hound::Error) -> Self {
+    match e {
+      hound::Error::Unsupported => WavError::Unsupported("not a PCM or IEEE float WAV".into()),
+      // hound 打开文件时发现 data 块的样本数不是声道数的整数倍
+      hound::Error::FormatError("invalid data chunk length") => {
+        WavError::Invalid(NOT_WHOLE_FRAMES.into())
+      }
+      e => WavError::Read(e),
+    }
+  }
+}
+
+// 逐个读样本；文件在 data 块中途结束（IO 错误）时停下，由调用方按读到的长度报错
+fn collect<S: hound::Sample>(
+  reader: &mut WavReader<std::io::BufReader<std::fs::File>>,
+) -> Result<Vec<S>, WavError> {
+  let mut samples = Vec::with_capacity(reader.len() as usize);
+  for sample in reader.samples::<S>() {
+    match sample {
+      Ok(sample) => samples.push(sample),
+      Err(hound::Error::IoError(_)) => break,
+      Err(e) => return Err(e.into()),
+    }
+  }
+  Ok(samples)
+}
+
+// 只接受 16 位整数 PCM 和 32 位浮点；16 位与 addSamplePcm16 一样除以 32768
+pub fn read(path: &str) -> Result<WavData, WavError> {
+  let mut reader = WavReader::open(path)?;
+  let spec = reader.spec();
+  let samples = match (spec.sample_format, spec.bits_per_sample) {
+    (SampleFormat::Int, 16) => audio::pcm16_to_f32(&collect::<i16>(&mut reader)?),
+    (SampleFormat::Float, 32) => collect::<f32>(&mut reader)?,
+    (SampleFormat::Int, bits) => {
+      return Err(WavError::Unsupported(format!(
+        "{bits}-bit integer PCM (only 16-bit integer and 32-bit float are supported)"
+      )))
+    }
+    (SampleFormat::Float, bits) => {
+      return Err(WavError::Unsupported(format!(
+        "{bits}-bit float (only 16-bit integer and 32-bit float are supported)"
+      )))
+    }
+  };
+  if spec.channels == 0 {
+    return Err(WavError::Unsupported("0 channels".into()));
+  }
+  // 被截断的多声道文件最后一帧不完整，声道会错位
+  if !samples.len().is_multiple_of(spec.channels as usize) {
+    return Err(WavError::Invalid(format!(
+      "{NOT_WHOLE_FRAMES} ({} samples, {} channels)",
+      samples.len(),
+      spec.channels
+    )));
+  }
+  if samples.len() < reader.len() as usize {
+    return Err(WavError::Invalid(format!(
+      "WAV data is truncated ({} of {} samples)",
+      samples.len(),
+      reader.len()
+    )));
+  }
+  Ok(WavData {
+    samples,
+    channels: spec.channels as usize,
+    sample_rate: spec.sample_rate,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 16 位 PCM 的 WAV：data 块里写入 samples 个样本，不管是否凑满一帧
+  fn write_pcm16(path: &std::path::Path, channels: u16, samples: &[i16]) {
+    let data_len = (samples.len() * 2) as u32;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&16000u32.to_le_bytes());
+    bytes.extend_from_slice(&(16000 * 2 * channels as u32).to_le_bytes());
+    bytes.extend_from_slice(&(2 * channels).to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+      bytes.extend_from_slice(&s.to_le_bytes());
+    }
+    std::fs::write(path, bytes).unwrap();
+  }
+
+  #[test]
+  fn truncated_multichannel_wav_is_rejected() {
+    let dir = std::env::temp_dir();
+    let whole = dir.join(format!("ulipsync-wav-whole-{}.wav", std::process::id()));
+    let truncated = dir.join(format!("ulipsync-wav-truncated-{}.wav", std::process::id()));
+    let odd = dir.join(format!("ulipsync-wav-odd-{}.wav", std::process::id()));
+    write_pcm16(&whole, 2, &[0, 1, 2, 3]);
+    write_pcm16(&truncated, 2, &[0, 1, 2, 3]);
+    // 文件在最后一帧中间被截断，data 块的长度仍是完整的
+    let bytes = std::fs::read(&truncated).unwrap();
+    std::fs::write(&truncated, &bytes[..bytes.len() - 2]).unwrap();
+    // data 块本身就不是整帧
+    write_pcm16(&odd, 2, &[0, 1, 2]);
+    let results: Vec<_> = [&whole, &truncated, &odd]
+      .iter()
+      .map(|path| {
+        let result = read(path.to_str().unwrap());
+        std::fs::remove_file(path).unwrap();
+        result
+      })
+      .collect();
+    let [whole, truncated, odd] = <[_; 3]>::try_from(results).ok().unwrap();
+    assert!(whole.is_ok_and(|wav| wav.samples.len() == 4 && wav.channels == 2));
+    for result in [truncated, odd] {
+      assert!(matches!(result, Err(WavError::Invalid(msg)) if msg.starts_with(NOT_WHOLE_FRAMES)));
+    }
+  }
+}